@@ -0,0 +1,264 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+
+use crate::chain::Chain;
+use crate::config::{AppConfig, CoreKind};
+use crate::groups::{ProviderGroup, ProviderRule};
+use crate::mihomo::Mihomo;
+use crate::singbox::SingBox;
+use crate::subscription::ProxyNode;
+use crate::xray::Xray;
+
+/// Release of an installed core, used to pick between config formats and to
+/// reject cores that are too old.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CoreVersion {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl CoreVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses `1.11.4`, `v1.12.0-beta.3`, `25.1.30` and similar.
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim().trim_start_matches('v').split(['.', '-']);
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+
+    /// Fails with an upgrade hint when `feature` needs a newer `core`.
+    pub fn require(self, core: &str, feature: &str, minimum: Self) -> Result<()> {
+        if self < minimum {
+            bail!(
+                "{feature} requires {core} {minimum} or newer, but the installed core is {self}; run `route-cli upgrade-core`"
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CoreVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Why a core cannot run a node.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Unsupported {
+    /// A field the node type needs is not set.
+    MissingField(&'static str),
+    /// A node type the core has no outbound for.
+    Type(String),
+    /// A shadowsocks SIP003 plugin.
+    Plugin(String),
+    /// A `network` transport without an equivalent in the core.
+    Transport(String),
+    /// An option of an otherwise supported type, e.g. TLS on socks5.
+    Feature(&'static str),
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingField(field) => write!(f, "missing `{field}`"),
+            Self::Type(kind) => write!(f, "type '{kind}'"),
+            Self::Plugin(plugin) => write!(f, "ss plugin '{plugin}'"),
+            Self::Transport(network) => write!(f, "unmapped transport '{network}'"),
+            Self::Feature(feature) => write!(f, "{feature}"),
+        }
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Unwraps a node field the node type needs.
+pub fn required<T>(value: Option<T>, field: &'static str) -> Result<T, Unsupported> {
+    value.ok_or(Unsupported::MissingField(field))
+}
+
+/// Fails when `routing` asks for rule sets or DNS, which only the sing-box
+/// renderer emits.
+pub fn reject_sing_box_routing(cfg: &AppConfig, core: &str) -> Result<()> {
+    if !cfg.routing.rule_sets.is_empty() || cfg.routing.dns.is_some() {
+        bail!("routing.rule_sets and routing.dns need the sing-box core, not {core}");
+    }
+    Ok(())
+}
+
+/// What proxied traffic is sent through.
+pub enum Egress<'a> {
+    Node(&'a ProxyNode),
+    /// A provider group with the members the active core supports.
+    Group(&'a ProviderGroup, Vec<&'a ProxyNode>),
+}
+
+impl Egress<'_> {
+    /// The group's provider rules, when `routing.provider_rules` imports them.
+    pub fn provider_rules(&self, cfg: &AppConfig) -> &[ProviderRule] {
+        match self {
+            Egress::Group(group, _) if cfg.routing.provider_rules => &group.rules,
+            _ => &[],
+        }
+    }
+}
+
+/// A proxy core route-cli can install, configure and run.
+pub trait CoreBackend: Sync {
+    /// Name used in messages, `bin/<name>/` and `tools/<name>/` bundles.
+    fn name(&self) -> &'static str;
+    /// Executable file name on the host platform.
+    fn binary_name(&self) -> &'static str;
+    /// Extension of the generated config file.
+    fn config_extension(&self) -> &'static str;
+    /// Oldest release whose config format can be generated.
+    fn minimum_version(&self) -> CoreVersion;
+    /// Whether the core can run `node`, and why not. Backends derive this
+    /// from the same mapping `render_config` uses.
+    fn check(&self, node: &ProxyNode) -> Result<(), Unsupported>;
+    fn supports(&self, node: &ProxyNode) -> bool {
+        self.check(node).is_ok()
+    }
+    /// Renders a config that listens on `listen_port` and sends either all
+    /// traffic or only `routing.proxy_domains` through `egress`, dialed
+    /// through `chain`.
+    fn render_config(
+        &self,
+        cfg: &AppConfig,
+        egress: &Egress,
+        chain: &Chain,
+        listen_port: u16,
+        route_all: bool,
+        version: CoreVersion,
+    ) -> Result<String>;
+    fn run_args(&self, config_path: &Path) -> Vec<OsString>;
+    /// Arguments that validate a config without starting the core.
+    fn check_args(&self, config_path: &Path) -> Vec<OsString>;
+    fn version_args(&self) -> &'static [&'static str];
+    fn parse_version(&self, output: &str) -> Option<String> {
+        parse_version_output(output)
+    }
+    /// GitHub repository publishing releases, as `owner/name`.
+    fn release_repo(&self) -> &'static str;
+    /// Release asset for the host, e.g. `sing-box-1.12.0-linux-amd64.tar.gz`.
+    fn host_asset_name(&self, version: &str) -> Result<String>;
+}
+
+pub fn backend_for(kind: CoreKind) -> &'static dyn CoreBackend {
+    match kind {
+        CoreKind::SingBox => &SingBox,
+        CoreKind::Mihomo => &Mihomo,
+        CoreKind::Xray => &Xray,
+    }
+}
+
+/// Whether `path` is the bare binary name of any backend rather than a real
+/// path. Both platform spellings count, so a config copied between Windows
+/// and Unix keeps working.
+pub fn is_default_core_name(path: &str) -> bool {
+    CoreKind::value_variants().iter().any(|kind| {
+        let name = backend_for(*kind).name();
+        path == name
+            || path
+                .to_ascii_lowercase()
+                .strip_suffix(".exe")
+                .is_some_and(|stem| stem == name)
+    })
+}
+
+/// Picks the first version-looking word from the first line of a core's
+/// version output, e.g. `sing-box version 1.12.20` or `Mihomo Meta v1.19.1`.
+pub fn parse_version_output(output: &str) -> Option<String> {
+    output.lines().next()?.split_whitespace().find_map(|word| {
+        let version = word.trim_start_matches('v');
+        let looks_like_version = version.starts_with(|c: char| c.is_ascii_digit())
+            && version.contains('.')
+            && CoreVersion::parse(version).is_some();
+        looks_like_version.then(|| version.to_string())
+    })
+}
+
+/// Maps the host to a release naming scheme's OS and architecture labels.
+pub fn host_labels<'a>(
+    core: &str,
+    os_labels: [&'a str; 3],
+    arch_labels: [&'a str; 3],
+) -> Result<(&'a str, &'a str)> {
+    let os = match std::env::consts::OS {
+        "windows" => os_labels[0],
+        "linux" => os_labels[1],
+        "macos" => os_labels[2],
+        other => bail!("No {core} release is published for OS '{other}'"),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => arch_labels[0],
+        "aarch64" => arch_labels[1],
+        "x86" => arch_labels[2],
+        other => bail!("No {core} release is published for architecture '{other}'"),
+    };
+    Ok((os, arch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CoreVersion, is_default_core_name, parse_version_output};
+
+    #[test]
+    fn parses_core_versions() {
+        assert_eq!(
+            CoreVersion::parse("1.11.4"),
+            Some(CoreVersion::new(1, 11, 4))
+        );
+        assert_eq!(
+            CoreVersion::parse("v1.12.0-beta.3"),
+            Some(CoreVersion::new(1, 12, 0))
+        );
+        assert_eq!(CoreVersion::parse("unknown"), None);
+        assert!(CoreVersion::new(1, 9, 0) < CoreVersion::new(1, 10, 0));
+        assert!(
+            CoreVersion::new(1, 7, 0)
+                .require("sing-box", "x", CoreVersion::new(1, 8, 0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn reads_version_from_each_core() {
+        for (output, expected) in [
+            (
+                "sing-box version 1.12.20\n\nEnvironment: go1.24.5",
+                "1.12.20",
+            ),
+            ("Mihomo Meta v1.19.1 linux amd64 with go1.23.4", "1.19.1"),
+            (
+                "Xray 25.1.30 (Xray, Penetrates Everything.) 3a4c5f9",
+                "25.1.30",
+            ),
+        ] {
+            assert_eq!(parse_version_output(output).as_deref(), Some(expected));
+        }
+        assert_eq!(parse_version_output("garbage"), None);
+    }
+
+    #[test]
+    fn recognizes_bare_core_names() {
+        assert!(is_default_core_name("sing-box"));
+        assert!(is_default_core_name("Mihomo.EXE"));
+        assert!(is_default_core_name("xray"));
+        assert!(!is_default_core_name("/opt/xray"));
+    }
+}
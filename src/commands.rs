@@ -1,42 +1,51 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::{Cursor, Read};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, bail};
+use serde::Serialize;
 use tokio::process::Command;
-use zip::ZipArchive;
 
-use crate::config::{AppPaths, load_config, resolve_proxy_core_path, save_config};
-use crate::proxy::{generate_sing_box_config, spawn_proxy_core, stop_process, wait_port_open};
+use crate::backend::{CoreBackend, CoreVersion, Egress, Unsupported, backend_for};
+use crate::chain::Chain;
+use crate::config::{
+    AppConfig, AppPaths, CoreKind, SecretStore, SubscriptionConfig, SubscriptionFormat,
+    file_subscription_url, load_config, resolve_proxy_core_path, save_config,
+    validate_subscription_name, validate_subscription_url,
+};
+use crate::diff::{NodeDiff, diff_nodes};
+use crate::groups::{ProviderGroup, load_provider_groups};
+use crate::install::{
+    Release, asset_name_from_url, compare_versions, detect_core_version, download_and_install_core,
+    finalize_install, install_core_from_local_bundle, installed_versions, normalize_sha256,
+    normalize_version, prepare_staging, published_sha256, resolve_release, sha256_file,
+    version_dir,
+};
+use crate::latency::{
+    LatencyRecord, load_latencies, node_latency, parse_ping_time, record_latencies,
+};
+use crate::net::DownloadClient;
+use crate::output::{OutputFormat, Report, print_json};
+use crate::picker::pick_node;
+use crate::proxy::{
+    check_core_config, generate_core_config, resolve_core_version, spawn_proxy_core, stop_process,
+    wait_port_open,
+};
+use crate::region::{node_region, region_priority};
+use crate::secrets::{Secrets, redact_url, unlock_urls};
+use crate::selector::{resolve_selected, select_nodes};
 use crate::subscription::{
-    ProxyNode, download_subscription, parse_subscription, read_cached_subscription,
+    DownloadOutcome, ProxyNode, download_subscription, import_subscription, is_cache_stale,
+    load_local_nodes, load_node_pool, parse_subscription, read_cached_subscription,
+    read_local_subscription, read_subscription_input, read_subscription_meta, unix_now,
 };
 
-const GITHUB_LATEST_RELEASE_API: &str =
-    "https://api.github.com/repos/SagerNet/sing-box/releases/latest";
-const TARGET_ASSET_SUFFIX: &str = "windows-amd64.zip";
-
-fn node_region_priority(name: &str) -> u8 {
-    let lower = name.to_lowercase();
-    if name.contains("新加坡") || lower.contains("singapore") || lower.contains("sg") {
-        return 0;
-    }
-    if name.contains("韩国") || lower.contains("korea") || lower.contains("kr") {
-        return 1;
-    }
-    if name.contains("美国")
-        || lower.contains("united states")
-        || lower.contains("usa")
-        || lower.contains("us")
-    {
-        return 2;
-    }
-    3
-}
+const BACKGROUND_REFRESH_WAIT: Duration = Duration::from_secs(10);
 
-async fn ping_reachable(host: &str) -> bool {
+/// Pings `host` once; the round trip in milliseconds, or `None` when it did not answer.
+async fn ping_latency(host: &str) -> Option<u64> {
     #[cfg(windows)]
     let mut cmd = {
         let mut c = Command::new("ping");
@@ -49,15 +58,16 @@ async fn ping_reachable(host: &str) -> bool {
         c.arg("-c").arg("1").arg("-W").arg("2").arg(host);
         c
     };
-    match cmd
-        .stdout(Stdio::null())
+    let started = Instant::now();
+    let output = cmd
+        .stdout(Stdio::piped())
         .stderr(Stdio::null())
-        .status()
+        .output()
         .await
-    {
-        Ok(s) => s.success(),
-        Err(_) => false,
-    }
+        .ok()
+        .filter(|o| o.status.success())?;
+    let elapsed = started.elapsed().as_millis() as u64;
+    Some(parse_ping_time(&String::from_utf8_lossy(&output.stdout)).unwrap_or(elapsed))
 }
 
 #[cfg(windows)]
@@ -116,394 +126,1412 @@ fn resolve_program_for_windows(program: &str) -> String {
     program.to_string()
 }
 
-fn pick_windows_amd64_asset(assets: &[serde_json::Value]) -> Option<(String, String)> {
-    for asset in assets {
-        let name = asset.get("name").and_then(|v| v.as_str())?;
-        let url = asset.get("browser_download_url").and_then(|v| v.as_str())?;
-        if name.ends_with(TARGET_ASSET_SUFFIX) && !url.is_empty() {
-            return Some((name.to_string(), url.to_string()));
+/// Parses a `Name: value` request header given on the command line.
+fn parse_header_arg(raw: &str) -> Result<(String, String)> {
+    let (name, value) = raw
+        .split_once(':')
+        .with_context(|| format!("Invalid header '{raw}', expected 'Name: value'"))?;
+    let name = name.trim();
+    let value = value.trim();
+    reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .with_context(|| format!("Invalid header name '{name}'"))?;
+    reqwest::header::HeaderValue::from_str(value)
+        .with_context(|| format!("Invalid value for header '{name}'"))?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Saves a subscription. Options not given on the command line keep their
+/// previous values when the subscription already exists.
+pub async fn cmd_login_sub(
+    name: String,
+    url: Option<String>,
+    file: Option<String>,
+    user_agent: Option<String>,
+    headers: Vec<String>,
+    format: Option<SubscriptionFormat>,
+    store: Option<SecretStore>,
+) -> Result<()> {
+    validate_subscription_name(&name)?;
+    let (url, body) = match (url, file.as_deref()) {
+        (Some(url), _) => {
+            validate_subscription_url(&url)?;
+            (Some(url), None)
         }
+        (None, Some("-")) => (None, Some(read_subscription_input("-")?)),
+        (None, Some(path)) => (Some(file_subscription_url(path)?), None),
+        (None, None) => bail!("Pass --url <URL> or --file <FILE|->"),
+    };
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let mut sub = cfg
+        .find_subscription(&name)
+        .cloned()
+        .unwrap_or_else(|| SubscriptionConfig::new(&name, None));
+    let remote = url
+        .as_deref()
+        .is_some_and(|url| url.starts_with("http://") || url.starts_with("https://"));
+    if store.is_some() && !remote {
+        bail!("`--store` only applies to http(s) subscription URLs");
+    }
+    let mut secrets = Secrets::new(&paths, true);
+    // A protected subscription stays protected when its URL is replaced, and
+    // keeps the headers stored with it.
+    let previous_store = sub.url_store;
+    if previous_store.is_some()
+        && remote
+        && let Err(err) = secrets.unlock(&mut sub)
+    {
+        println!("[WARN] {err:#}; its stored headers are dropped");
+    }
+    sub.url_store = store.or(previous_store).filter(|_| remote);
+    sub.url = url;
+    if user_agent.is_some() {
+        sub.user_agent = user_agent;
+    }
+    if let Some(format) = format {
+        sub.format = format;
+    }
+    for raw in &headers {
+        let (header, value) = parse_header_arg(raw)?;
+        sub.headers.insert(header, value);
+    }
+    if !sub.is_remote() {
+        // Cache local sources right away; they need no `update` to be usable.
+        let source = match &body {
+            Some(body) => UpdateSource::Body(body),
+            None => UpdateSource::Fetch(None),
+        };
+        let result = update_subscription(source, &cfg, &sub, &paths, false, false).await;
+        if let Some(err) = &result.error {
+            bail!("{err}");
+        }
+        result.print_table();
+    }
+    if let Some(store) = sub.url_store {
+        let secrets_kept = secret_parts(&sub);
+        secrets.protect(store, &mut sub)?;
+        println!(
+            "[OK] subscription {secrets_kept} stored in {}",
+            store_label(store)
+        );
+    }
+    if let Some(previous) = previous_store
+        && sub.url_store != Some(previous)
+    {
+        secrets.delete(previous, &name)?;
     }
-    None
+    cfg.upsert_subscription(sub);
+    save_config(&paths, &cfg)?;
+    println!(
+        "Subscription '{name}' saved to {}",
+        paths.config_toml.display()
+    );
+    Ok(())
 }
 
-async fn resolve_latest_sing_box_asset() -> Result<(String, String)> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(GITHUB_LATEST_RELEASE_API)
-        .header(reqwest::header::USER_AGENT, "route-cli")
-        .send()
-        .await
-        .context("Failed to query latest sing-box release")?;
-    let status = response.status();
-    if !status.is_success() {
-        bail!("Latest release request failed with status {status}");
+pub async fn cmd_remove_sub(name: String) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let url_store = cfg.find_subscription(&name).and_then(|s| s.url_store);
+    if !cfg.remove_subscription(&name) {
+        bail!("Subscription '{name}' is not configured");
     }
-    let payload_text = response
-        .text()
-        .await
-        .context("Failed to read latest release response body")?;
-    let payload: serde_json::Value = serde_json::from_str(&payload_text)
-        .context("Failed to parse latest release response JSON")?;
-    let assets = payload
-        .get("assets")
-        .and_then(|v| v.as_array())
-        .context("No assets found in latest release response")?;
-    pick_windows_amd64_asset(assets).context(
-        "No windows-amd64 release asset found. Use `route-cli install-core --url <zip-url>`",
-    )
+    save_config(&paths, &cfg)?;
+    if let Some(store) = url_store {
+        Secrets::new(&paths, false).delete(store, &name)?;
+    }
+    for cache in [
+        paths.subscription_cache(&name),
+        paths.subscription_backup(&name),
+        paths.subscription_meta(&name),
+    ] {
+        if cache.exists() {
+            fs::remove_file(&cache)
+                .with_context(|| format!("Failed to remove {}", cache.display()))?;
+        }
+    }
+    println!("Subscription '{name}' removed");
+    Ok(())
 }
 
-fn install_sing_box_zip(bytes: &[u8], target_dir: &Path) -> Result<PathBuf> {
-    fs::create_dir_all(target_dir)
-        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
-    let mut archive =
-        ZipArchive::new(Cursor::new(bytes)).context("Invalid sing-box zip archive")?;
-
-    let mut installed_exe: Option<PathBuf> = None;
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .with_context(|| format!("Failed reading zip entry #{i}"))?;
-        if !entry.is_file() {
-            continue;
-        }
-        let Some(name) = entry.enclosed_name() else {
+/// Moves subscription URLs and headers from `config.toml` (or another store)
+/// into `store`.
+pub async fn cmd_protect_sub(name: Option<String>, store: SecretStore) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let names: Vec<String> = selected_subscriptions(&cfg, name.as_deref())?
+        .into_iter()
+        .map(|s| s.name.clone())
+        .collect();
+    let mut secrets = Secrets::new(&paths, true);
+    let mut moved = 0;
+    for sub_name in names {
+        let Some(sub) = cfg.subscriptions.iter_mut().find(|s| s.name == sub_name) else {
             continue;
         };
-        let Some(file_name) = name.file_name().and_then(|s| s.to_str()) else {
+        if !sub.is_remote() {
+            if name.is_some() {
+                bail!("Subscription '{sub_name}' has no http(s) URL to protect");
+            }
             continue;
-        };
-
-        let lower = file_name.to_ascii_lowercase();
-        let destination = if lower == "sing-box.exe" {
-            target_dir.join("sing-box.exe")
-        } else if lower.ends_with(".dll") {
-            target_dir.join(file_name)
-        } else {
+        }
+        if sub.url_store == Some(store) && sub.headers.is_empty() {
+            println!(
+                "[OK] subscription '{sub_name}' already stored in {}",
+                store_label(store)
+            );
             continue;
-        };
-
-        let mut buf = Vec::new();
-        entry
-            .read_to_end(&mut buf)
-            .with_context(|| format!("Failed to extract zip entry: {}", name.display()))?;
-        fs::write(&destination, buf)
-            .with_context(|| format!("Failed to write {}", destination.display()))?;
-        if lower == "sing-box.exe" {
-            installed_exe = Some(destination);
         }
+        let previous = sub.url_store;
+        secrets.unlock(sub)?;
+        let secrets_moved = secret_parts(sub);
+        secrets.protect(store, sub)?;
+        if let Some(previous) = previous
+            && previous != store
+        {
+            secrets.delete(previous, &sub_name)?;
+        }
+        // Saved after each move so a later failure leaves no URL in two places.
+        save_config(&paths, &cfg)?;
+        moved += 1;
+        println!(
+            "[OK] subscription '{sub_name}' {secrets_moved} moved to {}",
+            store_label(store)
+        );
+    }
+    if moved == 0 && name.is_none() {
+        println!(
+            "No subscription URL left in {}",
+            paths.config_toml.display()
+        );
     }
+    Ok(())
+}
 
-    installed_exe.context("sing-box.exe not found in downloaded zip archive")
+fn secret_parts(sub: &SubscriptionConfig) -> &'static str {
+    if sub.headers.is_empty() {
+        "URL"
+    } else {
+        "URL and headers"
+    }
 }
 
-fn local_bundle_candidates() -> Vec<PathBuf> {
-    let mut candidates = Vec::new();
-    let mut push_unique = |p: PathBuf| {
-        if !candidates.iter().any(|x| x == &p) {
-            candidates.push(p);
-        }
-    };
+fn store_label(store: SecretStore) -> &'static str {
+    match store {
+        SecretStore::Keyring => "the OS keyring",
+        SecretStore::File => "secrets.json",
+    }
+}
 
-    push_unique(PathBuf::from("tools").join("sing-box"));
-    if let Ok(cwd) = std::env::current_dir() {
-        push_unique(cwd.join("tools").join("sing-box"));
+pub async fn cmd_add_node(node: String) -> Result<()> {
+    let node: ProxyNode = serde_yaml::from_str(&node)
+        .context("Invalid node; pass a Clash proxy entry, e.g. '{ name: office, type: socks5, server: 10.0.0.1, port: 1080 }'")?;
+    if node.name.trim().is_empty() {
+        bail!("Node name must not be empty");
     }
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(exe_dir) = exe.parent() {
-            push_unique(exe_dir.join("tools").join("sing-box"));
-        }
+    if node.server.is_none() || node.port.is_none() {
+        bail!("Node '{}' needs a server and a port", node.name);
     }
-    candidates
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    if let Err(reason) = backend.check(&node) {
+        println!(
+            "[WARN] node '{}' is not supported by {}: {reason}",
+            node.name,
+            backend.name()
+        );
+    }
+    let name = node.name.clone();
+    let replaced = cfg.upsert_node(node);
+    save_config(&paths, &cfg)?;
+    println!(
+        "Node '{name}' {} in {}",
+        if replaced { "updated" } else { "added" },
+        paths.config_toml.display()
+    );
+    Ok(())
 }
 
-fn install_sing_box_from_local_bundle(target_dir: &Path) -> Result<Option<(PathBuf, PathBuf)>> {
-    for source_dir in local_bundle_candidates() {
-        let source_exe = source_dir.join("sing-box.exe");
-        if !source_exe.exists() {
-            continue;
+pub async fn cmd_remove_node(name: String) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    if !cfg.remove_node(&name) {
+        let in_file = load_local_nodes(&cfg, &paths)?
+            .iter()
+            .any(|n| n.name == name);
+        if in_file {
+            bail!(
+                "Node '{name}' is defined in {}; edit that file to remove it",
+                paths.nodes_yaml.display()
+            );
         }
+        bail!("Node '{name}' is not a local node");
+    }
+    if cfg.runtime.selected_node.as_deref() == Some(name.as_str()) {
+        cfg.runtime.clear_node();
+    }
+    save_config(&paths, &cfg)?;
+    println!("Node '{name}' removed");
+    Ok(())
+}
 
-        fs::create_dir_all(target_dir)
-            .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+pub async fn cmd_install_core(
+    url: Option<String>,
+    sha256: Option<String>,
+    version: Option<String>,
+    kind: Option<CoreKind>,
+    no_verify: bool,
+) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let kind = kind.unwrap_or(cfg.proxy_core.kind);
+    let backend = backend_for(kind);
+    let expected_sha256 = sha256.as_deref().map(normalize_sha256).transpose()?;
+    let bin_dir = paths.core_bin_dir(backend);
+    let staging = prepare_staging(&bin_dir)?;
 
-        let target_exe = target_dir.join("sing-box.exe");
-        fs::copy(&source_exe, &target_exe).with_context(|| {
-            format!(
-                "Failed to copy {} to {}",
-                source_exe.display(),
-                target_exe.display()
-            )
-        })?;
-
-        for entry in fs::read_dir(&source_dir)
-            .with_context(|| format!("Failed to read {}", source_dir.display()))?
-        {
-            let entry = entry.with_context(|| {
-                format!("Failed to read directory entry in {}", source_dir.display())
-            })?;
-            let file_type = entry
-                .file_type()
-                .with_context(|| format!("Failed to get type for {}", entry.path().display()))?;
-            if !file_type.is_file() {
-                continue;
-            }
-            let path = entry.path();
-            let ext = path
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or_default();
-            if !ext.eq_ignore_ascii_case("dll") {
-                continue;
-            }
-            let file_name = entry.file_name();
-            let target_path = target_dir.join(&file_name);
-            fs::copy(&path, &target_path).with_context(|| {
-                format!(
-                    "Failed to copy {} to {}",
-                    path.display(),
-                    target_path.display()
+    // An explicit URL, version or checksum asks for a verified download, so
+    // the local bundle is only used when none is given.
+    let local_bundle = match (&url, &version, &expected_sha256) {
+        (None, None, None) => install_core_from_local_bundle(backend, &staging)?,
+        _ => None,
+    };
+    let (staged_exe, release_version) = match local_bundle {
+        Some((source_dir, exe)) => {
+            println!(
+                "Installed {} from local bundle: {}",
+                backend.name(),
+                source_dir.display()
+            );
+            (exe, None)
+        }
+        None => {
+            let downloader = connect_downloader(&cfg, &paths).await?;
+            let installed = match url {
+                Some(u) => download_and_install_core(
+                    &downloader.client,
+                    backend,
+                    &asset_name_from_url(&u),
+                    &u,
+                    expected_sha256.as_deref(),
+                    no_verify,
+                    &staging,
                 )
-            })?;
+                .await
+                .map(|exe| (exe, None)),
+                None => {
+                    match resolve_release(&downloader.client, backend, version.as_deref()).await {
+                        Ok(release) => {
+                            install_release(
+                                &downloader.client,
+                                backend,
+                                &release,
+                                expected_sha256,
+                                no_verify,
+                                &staging,
+                            )
+                            .await
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+            };
+            downloader.finish().await?;
+            installed?
         }
+    };
 
-        return Ok(Some((source_dir, target_exe)));
+    if kind != cfg.proxy_core.kind {
+        // Versions of the previous core cannot be rolled back to.
+        cfg.proxy_core.kind = kind;
+        cfg.proxy_core.version = None;
+        cfg.proxy_core.previous_version = None;
     }
-
-    Ok(None)
+    activate_staged_core(&mut cfg, &paths, &staged_exe, release_version).await
 }
 
-async fn download_and_install_sing_box(
-    asset_name: &str,
-    download_url: &str,
-    target_dir: &Path,
-) -> Result<PathBuf> {
-    println!("Installing sing-box asset: {asset_name}");
-    println!("Download URL: {download_url}");
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(download_url)
-        .header(reqwest::header::USER_AGENT, "route-cli")
-        .send()
+pub async fn cmd_upgrade_core(no_verify: bool) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let bin_dir = paths.core_bin_dir(backend);
+    let downloader = connect_downloader(&cfg, &paths).await?;
+    let installed = async {
+        let release = resolve_release(&downloader.client, backend, None).await?;
+        let current = cfg.proxy_core.version.as_deref();
+        let active_binary = version_dir(&bin_dir, &release.version).join(backend.binary_name());
+        if current == Some(release.version.as_str()) && active_binary.exists() {
+            return Ok(None);
+        }
+        println!(
+            "Upgrading {} {} -> {}",
+            backend.name(),
+            current.unwrap_or("(unversioned)"),
+            release.version
+        );
+        let staging = prepare_staging(&bin_dir)?;
+        install_release(
+            &downloader.client,
+            backend,
+            &release,
+            None,
+            no_verify,
+            &staging,
+        )
         .await
-        .with_context(|| format!("Failed to download sing-box from {download_url}"))?;
-    let status = response.status();
-    if !status.is_success() {
-        bail!("Download request failed with status {status}");
+        .map(Some)
+    }
+    .await;
+    downloader.finish().await?;
+
+    match installed? {
+        Some((staged_exe, release_version)) => {
+            activate_staged_core(&mut cfg, &paths, &staged_exe, release_version).await
+        }
+        None => {
+            println!(
+                "[OK] {} {} is already the latest release",
+                backend.name(),
+                cfg.proxy_core.version.as_deref().unwrap_or_default()
+            );
+            Ok(())
+        }
     }
-    let archive_bytes = response
-        .bytes()
-        .await
-        .context("Failed to read sing-box archive response bytes")?;
-    install_sing_box_zip(archive_bytes.as_ref(), target_dir)
 }
 
-pub async fn cmd_login_sub(url: String) -> Result<()> {
+pub async fn cmd_rollback_core(to: Option<String>) -> Result<()> {
     let paths = AppPaths::discover()?;
     let mut cfg = load_config(&paths)?;
-    cfg.subscription.url = Some(url);
+    let backend = backend_for(cfg.proxy_core.kind);
+    let bin_dir = paths.core_bin_dir(backend);
+    let name = backend.name();
+    let versions = installed_versions(&bin_dir, backend.binary_name())?;
+    let current = cfg.proxy_core.version.clone();
+
+    let target = match to {
+        Some(v) => normalize_version(&v),
+        None => cfg
+            .proxy_core
+            .previous_version
+            .clone()
+            .filter(|v| versions.contains(v))
+            .or_else(|| {
+                versions
+                    .iter()
+                    .rev()
+                    .find(|v| match current.as_deref() {
+                        Some(current) => compare_versions(v, current).is_lt(),
+                        None => true,
+                    })
+                    .cloned()
+            })
+            .with_context(|| format!("No earlier {name} version is installed to roll back to"))?,
+    };
+    if !versions.contains(&target) {
+        bail!(
+            "{name} {target} is not installed (installed: {})",
+            if versions.is_empty() {
+                "none".to_string()
+            } else {
+                versions.join(", ")
+            }
+        );
+    }
+    if current.as_deref() == Some(target.as_str()) {
+        bail!("{name} {target} is already active");
+    }
+
+    let binary = version_dir(&bin_dir, &target).join(backend.binary_name());
+    switch_core(&mut cfg, &binary, &target)?;
     save_config(&paths, &cfg)?;
-    println!("Subscription URL saved to {}", paths.config_toml.display());
+    println!(
+        "[OK] rolled back to {name} {target}: {}",
+        cfg.proxy_core.path
+    );
     Ok(())
 }
 
-pub async fn cmd_install_core(url: Option<String>) -> Result<()> {
-    let paths = AppPaths::discover()?;
-    let mut cfg = load_config(&paths)?;
-    let install_dir = paths
-        .config_toml
-        .parent()
-        .context("Invalid config path: missing parent directory")?
-        .join("bin");
-
-    let installed_exe = match url {
-        Some(u) => download_and_install_sing_box("custom.zip", &u, &install_dir).await?,
-        None => {
-            if let Some((source_dir, exe)) = install_sing_box_from_local_bundle(&install_dir)? {
-                println!(
-                    "Installed sing-box from local bundle: {}",
-                    source_dir.display()
-                );
-                exe
+/// Downloads a resolved release into `staging`, preferring a user-supplied
+/// checksum over the one the release publishes.
+async fn install_release(
+    client: &reqwest::Client,
+    backend: &dyn CoreBackend,
+    release: &Release,
+    expected_sha256: Option<String>,
+    no_verify: bool,
+    staging: &Path,
+) -> Result<(PathBuf, Option<String>)> {
+    let expected_sha256 = match expected_sha256 {
+        Some(sha256) => Some(sha256),
+        None => published_sha256(client, &release.asset).await?,
+    };
+    let exe = download_and_install_core(
+        client,
+        backend,
+        &release.asset.name,
+        &release.asset.url,
+        expected_sha256.as_deref(),
+        no_verify,
+        staging,
+    )
+    .await?;
+    Ok((exe, Some(release.version.clone())))
+}
+
+/// Moves a staged core to its versioned directory and makes it the active core.
+async fn activate_staged_core(
+    cfg: &mut AppConfig,
+    paths: &AppPaths,
+    staged_exe: &Path,
+    release_version: Option<String>,
+) -> Result<()> {
+    let backend = backend_for(cfg.proxy_core.kind);
+    let name = backend.name();
+    let detected = detect_core_version(backend, &staged_exe.to_string_lossy()).await;
+    let version = match (release_version, detected) {
+        (Some(release), Some(detected)) if release != detected => {
+            println!("[WARN] release {release} reports version {detected}");
+            release
+        }
+        (Some(release), _) => release,
+        (None, Some(detected)) => detected,
+        (None, None) => {
+            println!("[WARN] could not detect the {name} version; installing as 'unknown'");
+            "unknown".to_string()
+        }
+    };
+    let installed = finalize_install(
+        &paths.core_bin_dir(backend),
+        &version,
+        backend.binary_name(),
+    )?;
+    switch_core(cfg, &installed, &version)?;
+    save_config(paths, cfg)?;
+
+    println!("[OK] {name} {version} installed: {}", cfg.proxy_core.path);
+    if let Some(previous) = cfg.proxy_core.previous_version.as_deref() {
+        println!("Previous version {previous} kept; use `route-cli rollback-core` to restore it");
+    }
+    println!("Updated config: {}", paths.config_toml.display());
+    Ok(())
+}
+
+/// Points the config at an installed core, remembering the version it replaces.
+fn switch_core(cfg: &mut AppConfig, binary: &Path, version: &str) -> Result<()> {
+    if let Some(current) = cfg.proxy_core.version.take()
+        && current != version
+    {
+        cfg.proxy_core.previous_version = Some(current);
+    }
+    cfg.proxy_core.version = Some(version.to_string());
+    cfg.proxy_core.path = binary.to_string_lossy().into_owned();
+    cfg.proxy_core.sha256 = Some(sha256_file(binary)?);
+    Ok(())
+}
+
+fn selected_subscriptions<'a>(
+    cfg: &'a AppConfig,
+    name: Option<&str>,
+) -> Result<Vec<&'a SubscriptionConfig>> {
+    if cfg.subscriptions.is_empty() {
+        bail!("No subscription configured. Run `route-cli login-sub --url <URL>`");
+    }
+    match name {
+        Some(name) => {
+            let sub = cfg
+                .find_subscription(name)
+                .with_context(|| format!("Subscription '{name}' is not configured"))?;
+            Ok(vec![sub])
+        }
+        None => Ok(cfg.subscriptions.iter().collect()),
+    }
+}
+
+/// Connects the client used for route-cli's own downloads and reports any
+/// fallback it had to take.
+async fn connect_downloader(cfg: &AppConfig, paths: &AppPaths) -> Result<DownloadClient> {
+    let downloader = DownloadClient::connect(cfg, paths).await?;
+    for warning in &downloader.warnings {
+        println!("[WARN] {warning}");
+    }
+    Ok(downloader)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum UpdateStatus {
+    Updated,
+    NotModified,
+    Failed,
+}
+
+impl UpdateStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Updated => "updated",
+            Self::NotModified => "not_modified",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Outcome of updating one subscription, as printed by `update --output json`.
+#[derive(Debug, Serialize)]
+struct UpdateResult {
+    subscription: String,
+    status: UpdateStatus,
+    /// Nodes in the new cache; only set when the subscription was downloaded.
+    nodes: Option<usize>,
+    cache: PathBuf,
+    /// The download was only compared with the cache, not written.
+    dry_run: bool,
+    /// Node changes against the previous cache; unset without one.
+    diff: Option<NodeDiff>,
+    warnings: Vec<String>,
+    error: Option<String>,
+}
+
+impl UpdateResult {
+    fn print_table(&self) {
+        match self.status {
+            UpdateStatus::Updated if self.dry_run => println!(
+                "Subscription '{}' has {} nodes (dry run, cache unchanged)",
+                self.subscription,
+                self.nodes.unwrap_or_default()
+            ),
+            UpdateStatus::Updated => println!(
+                "Subscription '{}' updated: {} nodes cached at {}",
+                self.subscription,
+                self.nodes.unwrap_or_default(),
+                self.cache.display()
+            ),
+            UpdateStatus::NotModified => println!(
+                "Subscription '{}' not modified since last update",
+                self.subscription
+            ),
+            UpdateStatus::Failed => println!(
+                "[FAIL] subscription '{}': {}",
+                self.subscription,
+                self.error.as_deref().unwrap_or_default()
+            ),
+        }
+        if let Some(diff) = &self.diff {
+            if diff.is_empty() {
+                println!("  no node changes");
             } else {
-                let (asset_name, download_url) = resolve_latest_sing_box_asset().await?;
-                download_and_install_sing_box(&asset_name, &download_url, &install_dir).await?
+                println!("  {}", diff.summary());
+            }
+            for name in &diff.added {
+                println!("  + {name}");
+            }
+            for name in &diff.removed {
+                println!("  - {name}");
             }
+            for change in &diff.changed {
+                match &change.previous_name {
+                    Some(previous) => println!(
+                        "  ~ {} (was {previous}): {}",
+                        change.name,
+                        change.fields.join(", ")
+                    ),
+                    None => println!("  ~ {}: {}", change.name, change.fields.join(", ")),
+                }
+            }
+        }
+        for warning in &self.warnings {
+            println!("[WARN] subscription '{}': {warning}", self.subscription);
         }
+    }
+}
+
+/// Where `update_subscription` gets the new subscription body.
+enum UpdateSource<'a> {
+    /// Download `http(s)://` subscriptions and re-read `file://` ones.
+    Fetch(Option<&'a reqwest::Client>),
+    /// A body read by `update --from` or `login-sub --file -`.
+    Body(&'a str),
+}
+
+async fn update_subscription(
+    source: UpdateSource<'_>,
+    cfg: &AppConfig,
+    sub: &SubscriptionConfig,
+    paths: &AppPaths,
+    force: bool,
+    dry_run: bool,
+) -> UpdateResult {
+    let mut result = UpdateResult {
+        subscription: sub.name.clone(),
+        status: UpdateStatus::NotModified,
+        nodes: None,
+        cache: paths.subscription_cache(&sub.name),
+        dry_run,
+        diff: None,
+        warnings: Vec::new(),
+        error: None,
     };
-    cfg.proxy_core.path = installed_exe.to_string_lossy().into_owned();
-    save_config(&paths, &cfg)?;
+    let previous = paths
+        .subscription_cache(&sub.name)
+        .exists()
+        .then(|| {
+            read_cached_subscription(paths, &sub.name).and_then(|raw| parse_subscription(&raw))
+        })
+        .and_then(Result::ok);
+    let outcome = match source {
+        UpdateSource::Body(body) => import_subscription(paths, sub, body, dry_run),
+        UpdateSource::Fetch(Some(client)) if sub.is_remote() => {
+            download_subscription(client, sub, paths, force, dry_run).await
+        }
+        UpdateSource::Fetch(_) => read_local_subscription(paths, sub, dry_run),
+    };
+    match outcome {
+        Ok(DownloadOutcome::Updated(nodes)) => {
+            result.status = UpdateStatus::Updated;
+            result.nodes = Some(nodes.len());
+            result.diff = previous.map(|previous| diff_nodes(&previous, &nodes));
+        }
+        Ok(DownloadOutcome::NotModified) => {}
+        Err(err) => {
+            result.status = UpdateStatus::Failed;
+            result.error = Some(format!("{err:#}"));
+            return result;
+        }
+    }
+    result.warnings = subscription_warnings(cfg, sub, paths);
+    result
+}
 
-    let core_check = Command::new(&cfg.proxy_core.path)
-        .arg("version")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output()
-        .await;
+struct RefreshReport {
+    warnings: Vec<String>,
+    results: Vec<(String, Result<DownloadOutcome>)>,
+}
 
-    match core_check {
-        Ok(output) if output.status.success() => {
-            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if version.is_empty() {
-                println!("[OK] sing-box installed: {}", cfg.proxy_core.path);
-            } else {
+/// Refreshes stale subscription caches without printing, so it can run while
+/// the target command owns the terminal.
+async fn refresh_stale_subscriptions(
+    cfg: AppConfig,
+    subs: Vec<SubscriptionConfig>,
+    paths: AppPaths,
+) -> Result<RefreshReport> {
+    let downloader = DownloadClient::connect(&cfg, &paths).await?;
+    let mut results = Vec::new();
+    for sub in subs {
+        let result = download_subscription(&downloader.client, &sub, &paths, false, false).await;
+        results.push((sub.name, result));
+    }
+    let warnings = downloader.warnings.clone();
+    downloader.finish().await?;
+    Ok(RefreshReport { warnings, results })
+}
+
+async fn report_background_refresh(
+    handle: tokio::task::JoinHandle<Result<RefreshReport>>,
+    paths: &AppPaths,
+) {
+    let abort = handle.abort_handle();
+    let report = match tokio::time::timeout(BACKGROUND_REFRESH_WAIT, handle).await {
+        Ok(Ok(Ok(report))) => report,
+        Ok(Ok(Err(err))) => {
+            println!("[WARN] background subscription refresh failed: {err:#}");
+            return;
+        }
+        Ok(Err(err)) => {
+            println!("[WARN] background subscription refresh failed: {err}");
+            return;
+        }
+        Err(_) => {
+            abort.abort();
+            println!("[WARN] background subscription refresh timed out; cache left unchanged");
+            return;
+        }
+    };
+    for warning in &report.warnings {
+        println!("[WARN] {warning}");
+    }
+    let now = unix_now();
+    for (name, result) in report.results {
+        match result {
+            Ok(DownloadOutcome::Updated(nodes)) => {
                 println!(
-                    "[OK] sing-box installed: {} ({version})",
-                    cfg.proxy_core.path
+                    "[OK] subscription '{name}' refreshed: {} nodes",
+                    nodes.len()
+                )
+            }
+            Ok(DownloadOutcome::NotModified) => {}
+            Err(err) => {
+                let age = read_subscription_meta(paths, &name)
+                    .ok()
+                    .flatten()
+                    .map(|m| format!("{}h old", m.age_secs(now) / 3_600))
+                    .unwrap_or_else(|| "unknown age".to_string());
+                println!(
+                    "[WARN] subscription '{name}' refresh failed, kept stale cache ({age}): {err:#}"
                 );
             }
         }
-        Ok(_) => {
-            println!(
-                "[WARN] sing-box installed at {} but `version` returned non-zero",
-                cfg.proxy_core.path
+    }
+}
+
+fn subscription_warnings(
+    cfg: &AppConfig,
+    sub: &SubscriptionConfig,
+    paths: &AppPaths,
+) -> Vec<String> {
+    let Ok(Some(meta)) = read_subscription_meta(paths, &sub.name) else {
+        return Vec::new();
+    };
+    let Some(info) = meta.userinfo else {
+        return Vec::new();
+    };
+    info.warnings(&cfg.subscription_alerts, unix_now())
+}
+
+/// Adds traffic/expiry details for one subscription to `report`.
+/// Warns about http(s) URLs and request headers, and the tokens in them,
+/// kept in `config.toml`.
+fn report_url_storage(report: &mut Report, sub: &SubscriptionConfig) {
+    let id = format!("subscription.{}", sub.name);
+    let plaintext = match sub.url_store {
+        Some(store) if sub.headers.is_empty() => {
+            report.ok(
+                format!("{id}.url"),
+                format!(
+                    "subscription '{}' URL kept in {}",
+                    sub.name,
+                    store_label(store)
+                ),
             );
+            return;
+        }
+        Some(_) => "request headers are",
+        None if !sub.is_remote() => return,
+        None if sub.headers.is_empty() => "URL is",
+        None => "URL and request headers are",
+    };
+    report.warn(
+        format!("{id}.url"),
+        format!(
+            "subscription '{0}' {plaintext} in plaintext in config.toml; run `route-cli protect-sub {0} --store keyring|file`",
+            sub.name
+        ),
+    );
+}
+
+fn report_subscription_info(
+    report: &mut Report,
+    cfg: &AppConfig,
+    sub: &SubscriptionConfig,
+    paths: &AppPaths,
+) {
+    let id = format!("subscription.{}", sub.name);
+    let meta = match read_subscription_meta(paths, &sub.name) {
+        Ok(Some(meta)) => meta,
+        Ok(None) => {
+            report.warn(
+                format!("{id}.usage"),
+                format!(
+                    "subscription '{}': no usage info cached, run `route-cli update`",
+                    sub.name
+                ),
+            );
+            return;
         }
         Err(err) => {
-            println!(
-                "[WARN] sing-box installed at {} but failed to run `version`: {err}",
-                cfg.proxy_core.path
+            report.err(
+                format!("{id}.usage"),
+                format!("subscription '{}': {err:#}", sub.name),
             );
+            return;
         }
+    };
+    match &meta.userinfo {
+        Some(info) => report.ok(
+            format!("{id}.usage"),
+            format!(
+                "subscription '{}' usage: {}",
+                sub.name,
+                info.summary(unix_now())
+            ),
+        ),
+        None => report.ok(
+            format!("{id}.usage"),
+            format!(
+                "subscription '{}': provider did not report usage info",
+                sub.name
+            ),
+        ),
+    }
+    if sub.is_remote() {
+        report.ok(
+            format!("{id}.request"),
+            format!(
+                "subscription '{}' requested with User-Agent '{}', received {}",
+                sub.name,
+                sub.effective_user_agent(),
+                meta.content_type
+                    .as_deref()
+                    .unwrap_or("unknown content type")
+            ),
+        );
+    } else {
+        report.ok(
+            format!("{id}.request"),
+            format!(
+                "subscription '{}' imported from {}",
+                sub.name,
+                sub.url.as_deref().unwrap_or("stdin")
+            ),
+        );
+    }
+    for warning in subscription_warnings(cfg, sub, paths) {
+        report.warn(
+            format!("{id}.alert"),
+            format!("subscription '{}': {warning}", sub.name),
+        );
     }
+}
 
-    println!("Updated config: {}", paths.config_toml.display());
-    Ok(())
+/// Prints a message that is not part of the `--output` result; json and
+/// plain keep stdout parseable by sending it to stderr.
+fn print_notice(output: OutputFormat, line: &str) {
+    match output {
+        OutputFormat::Table => println!("{line}"),
+        OutputFormat::Json | OutputFormat::Plain => eprintln!("{line}"),
+    }
 }
 
-pub async fn cmd_update() -> Result<()> {
-    let paths = AppPaths::discover()?;
-    let cfg = load_config(&paths)?;
-    let url = cfg
-        .subscription
-        .url
-        .as_deref()
-        .context("No subscription URL configured. Run `route-cli login-sub --url <URL>`")?;
-    let raw = download_subscription(url, &paths).await?;
-    let nodes = parse_subscription(&raw)?;
-    println!(
-        "Subscription updated: {} nodes cached at {}",
-        nodes.len(),
-        paths.subscription_yaml.display()
-    );
+/// Follows the selected node across provider renames after an update and
+/// reports when it is gone.
+fn refresh_selection(cfg: &mut AppConfig, paths: &AppPaths, output: OutputFormat) -> Result<()> {
+    let Some(name) = cfg.runtime.selected_node.clone() else {
+        return Ok(());
+    };
+    let Ok(nodes) = load_node_pool(cfg, paths) else {
+        return Ok(());
+    };
+    match resolve_selected(&cfg.runtime, &nodes) {
+        Some(node) if cfg.runtime.is_selected(node) => {}
+        Some(node) => {
+            if node.name != name {
+                print_notice(
+                    output,
+                    &format!("[OK] selected node '{name}' was renamed to '{}'", node.name),
+                );
+            }
+            cfg.runtime.select_node(node);
+            save_config(paths, cfg)?;
+        }
+        None => print_notice(
+            output,
+            &format!(
+                "[WARN] selected node '{name}' is no longer in the subscriptions; `run` will pick another reachable node"
+            ),
+        ),
+    }
     Ok(())
 }
 
-pub async fn cmd_list_nodes() -> Result<()> {
+pub async fn cmd_update(
+    name: Option<String>,
+    force: bool,
+    dry_run: bool,
+    from: Option<String>,
+    output: OutputFormat,
+) -> Result<()> {
     let paths = AppPaths::discover()?;
-    let raw = read_cached_subscription(&paths)?;
-    let nodes = parse_subscription(&raw)?;
-    for (idx, node) in nodes.iter().enumerate() {
-        let support = if node.is_supported_for_sing_box() {
-            "supported"
-        } else {
-            "unsupported"
+    let mut cfg = load_config(&paths)?;
+    let mut subs: Vec<SubscriptionConfig> = selected_subscriptions(&cfg, name.as_deref())?
+        .into_iter()
+        .cloned()
+        .collect();
+    if from.is_some() && subs.len() != 1 {
+        bail!(
+            "`--from` imports a single subscription; pass its name: route-cli update <NAME> --from <FILE|->"
+        );
+    }
+    let body = from.as_deref().map(read_subscription_input).transpose()?;
+    if body.is_none() {
+        unlock_urls(&mut subs, &paths, true)?;
+    }
+    if body.is_none() && name.is_none() {
+        subs.retain(|sub| {
+            if sub.url.is_none() {
+                print_notice(
+                    output,
+                    &format!(
+                        "Subscription '{0}' has no URL, skipped; refresh it with `route-cli update {0} --from <FILE|->`",
+                        sub.name
+                    ),
+                );
+            }
+            sub.url.is_some()
+        });
+    }
+
+    // Only `http(s)://` sources need the network (and a bootstrap core).
+    let downloader = if body.is_none() && subs.iter().any(|s| s.is_remote()) {
+        let downloader = DownloadClient::connect(&cfg, &paths).await?;
+        for warning in &downloader.warnings {
+            print_notice(output, &format!("[WARN] {warning}"));
+        }
+        Some(downloader)
+    } else {
+        None
+    };
+    let mut results = Vec::new();
+    for sub in &subs {
+        let source = match &body {
+            Some(body) => UpdateSource::Body(body),
+            None => UpdateSource::Fetch(downloader.as_ref().map(|d| &d.client)),
         };
-        println!(
-            "{:03} | {} | {} | {}",
-            idx + 1,
-            node.name,
-            node.node_type,
-            support
+        let result = update_subscription(source, &cfg, sub, &paths, force, dry_run).await;
+        if output == OutputFormat::Table {
+            result.print_table();
+        }
+        results.push(result);
+    }
+    if let Some(downloader) = downloader {
+        downloader.finish().await?;
+    }
+    match output {
+        OutputFormat::Table => {}
+        OutputFormat::Json => print_json(&results)?,
+        OutputFormat::Plain => {
+            for result in &results {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    result.subscription,
+                    result.status.as_str(),
+                    result.nodes.map(|n| n.to_string()).unwrap_or_default(),
+                    result.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+    }
+    if !dry_run {
+        refresh_selection(&mut cfg, &paths, output)?;
+    }
+    let failed = results
+        .iter()
+        .filter(|r| r.status == UpdateStatus::Failed)
+        .count();
+    if failed > 0 {
+        bail!(
+            "{failed} of {} subscription(s) failed to update",
+            subs.len()
         );
     }
     Ok(())
 }
 
-pub async fn cmd_use_node(node_name: String) -> Result<()> {
+pub async fn cmd_sub_info(name: Option<String>, output: OutputFormat) -> Result<()> {
     let paths = AppPaths::discover()?;
-    let mut cfg = load_config(&paths)?;
-    let raw = read_cached_subscription(&paths)?;
-    let nodes = parse_subscription(&raw)?;
-    let node = nodes
+    let cfg = load_config(&paths)?;
+    let mut report = Report::default();
+    for sub in selected_subscriptions(&cfg, name.as_deref())? {
+        report_url_storage(&mut report, sub);
+        report_subscription_info(&mut report, &cfg, sub, &paths);
+    }
+    report.print(output)
+}
+
+/// One `list-nodes` entry; field names are part of the `--output json` schema.
+#[derive(Debug, Serialize)]
+struct NodeEntry<'a> {
+    index: usize,
+    name: &'a str,
+    #[serde(rename = "type")]
+    node_type: &'a str,
+    server: Option<&'a str>,
+    port: Option<u16>,
+    region: Option<&'static str>,
+    source: &'a str,
+    supported: bool,
+    unsupported_reason: Option<String>,
+    latency_ms: Option<u64>,
+    latency_at: Option<u64>,
+    selected: bool,
+}
+
+pub async fn cmd_list_nodes(output: OutputFormat) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let cfg = load_config(&paths)?;
+    let nodes = load_node_pool(&cfg, &paths)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let latencies = load_latencies(&paths);
+    let selected = resolve_selected(&cfg.runtime, &nodes);
+    let entries: Vec<NodeEntry> = nodes
         .iter()
-        .find(|n| n.name == node_name)
-        .with_context(|| format!("Node '{node_name}' not found in cached subscription"))?;
-    if !node.is_supported_for_sing_box() {
+        .enumerate()
+        .map(|(idx, node)| {
+            let reason = backend.check(node).err();
+            let latency = node_latency(&latencies, node);
+            NodeEntry {
+                index: idx + 1,
+                name: &node.name,
+                node_type: &node.node_type,
+                server: node.server.as_deref(),
+                port: node.port,
+                region: node_region(&node.name),
+                source: &node.source,
+                supported: reason.is_none(),
+                unsupported_reason: reason.map(|r| r.to_string()),
+                latency_ms: latency.and_then(|l| l.ms),
+                latency_at: latency.map(|l| l.at),
+                selected: selected.is_some_and(|s| std::ptr::eq(s, node)),
+            }
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Json => print_json(&entries)?,
+        OutputFormat::Table => {
+            for entry in &entries {
+                let support = match &entry.unsupported_reason {
+                    None => "supported".to_string(),
+                    Some(reason) => format!("unsupported: {reason}"),
+                };
+                let latency = match (entry.latency_ms, entry.latency_at) {
+                    (Some(ms), _) => format!("{ms} ms"),
+                    (None, Some(_)) => "timeout".to_string(),
+                    (None, None) => "-".to_string(),
+                };
+                println!(
+                    "{:03} | {} | {} | {} | {}",
+                    entry.index, entry.name, entry.node_type, support, latency
+                );
+            }
+        }
+        OutputFormat::Plain => {
+            for entry in &entries {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    entry.index,
+                    entry.name,
+                    entry.node_type,
+                    entry.server.unwrap_or_default(),
+                    entry.port.map(|p| p.to_string()).unwrap_or_default(),
+                    entry.region.unwrap_or_default(),
+                    entry.unsupported_reason.as_deref().unwrap_or("supported"),
+                    entry
+                        .latency_ms
+                        .map(|ms| ms.to_string())
+                        .unwrap_or_default()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ensure_supported(backend: &dyn CoreBackend, node: &ProxyNode) -> Result<()> {
+    if let Err(reason) = backend.check(node) {
         bail!(
-            "Node '{}' type '{}' is not supported yet (supports socks5/socks/http/ss/vmess)",
+            "Node '{}' is not supported by {}: {reason}",
             node.name,
-            node.node_type
+            backend.name()
         );
     }
-    cfg.runtime.selected_node = Some(node_name.clone());
-    save_config(&paths, &cfg)?;
-    println!("Selected node: {node_name}");
     Ok(())
 }
 
-pub async fn cmd_run(command: Vec<String>) -> Result<i32> {
-    if command.is_empty() {
-        bail!("No command passed. Example: route-cli run -- claude");
+/// Resolves a selector without pinging: a `region:` selector yields the
+/// supported node with the lowest latency recorded by `run`.
+fn select_known_node<'a>(
+    paths: &AppPaths,
+    nodes: &'a [ProxyNode],
+    backend: &dyn CoreBackend,
+    selector: &str,
+) -> Result<&'a ProxyNode> {
+    match select_nodes(nodes, selector)?[..] {
+        [node] => Ok(node),
+        ref region => {
+            let latencies = load_latencies(paths);
+            region
+                .iter()
+                .copied()
+                .filter(|n| backend.supports(n))
+                .min_by_key(|n| {
+                    node_latency(&latencies, n)
+                        .and_then(|l| l.ms)
+                        .unwrap_or(u64::MAX)
+                })
+                .with_context(|| {
+                    format!(
+                        "No node matching '{selector}' is supported by {}",
+                        backend.name()
+                    )
+                })
+        }
     }
+}
 
+pub async fn cmd_use_node(node_name: Option<String>) -> Result<()> {
     let paths = AppPaths::discover()?;
     let mut cfg = load_config(&paths)?;
-    if cfg.subscription.url.is_none() {
-        bail!("No subscription URL configured. Run `route-cli login-sub --url <URL>`");
+    let nodes = load_node_pool(&cfg, &paths)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let node = match node_name {
+        // `region:` selectors persist the fastest supported node seen by `run`.
+        Some(selector) => select_known_node(&paths, &nodes, backend, &selector)?,
+        None => {
+            let supported: Vec<&ProxyNode> = nodes.iter().filter(|n| backend.supports(n)).collect();
+            let picked = pick_node(
+                &supported,
+                &load_latencies(&paths),
+                resolve_selected(&cfg.runtime, supported.iter().copied()).map(|n| n.name.as_str()),
+            )?;
+            let Some(node) = picked else {
+                println!("No node selected");
+                return Ok(());
+            };
+            node
+        }
+    };
+    ensure_supported(backend, node)?;
+    cfg.runtime.select_node(node);
+    cfg.runtime.selected_group = None;
+    save_config(&paths, &cfg)?;
+    println!("Selected node: {}", node.name);
+    Ok(())
+}
+
+pub async fn cmd_list_groups() -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let cfg = load_config(&paths)?;
+    let nodes = load_node_pool(&cfg, &paths)?;
+    let groups = load_provider_groups(&cfg, &paths, &nodes)?;
+    if groups.is_empty() {
+        println!("No proxy groups in the cached subscriptions");
+        return Ok(());
     }
-    if !paths.subscription_yaml.exists() {
-        cmd_update().await?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    for group in &groups {
+        let members = group.member_nodes(&nodes);
+        let supported = members.iter().filter(|n| backend.supports(n)).count();
+        let mut rules = format!("{} rules", group.rules.len());
+        if group.skipped_rules > 0 {
+            rules.push_str(&format!(" (+{} unsupported)", group.skipped_rules));
+        }
+        let active = cfg.runtime.selected_group.as_deref() == Some(group.name.as_str());
+        println!(
+            "{} {} | {} | {}/{} nodes supported | {rules}",
+            if active { "*" } else { " " },
+            group.name,
+            group.kind.clash_name(),
+            supported,
+            members.len()
+        );
     }
+    Ok(())
+}
 
-    let raw = read_cached_subscription(&paths)?;
-    let nodes = parse_subscription(&raw)?;
-    let supported: Vec<&ProxyNode> = nodes
-        .iter()
-        .filter(|n| n.is_supported_for_sing_box())
-        .collect();
-    if supported.is_empty() {
-        bail!("No supported node found. Use `route-cli list-nodes` then update subscription.");
+pub async fn cmd_use_group(group_name: String) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let nodes = load_node_pool(&cfg, &paths)?;
+    let groups = load_provider_groups(&cfg, &paths, &nodes)?;
+    if let Egress::Group(_, members) = group_egress(&cfg, &nodes, &groups, &group_name)? {
+        println!("Selected group: {group_name} ({} nodes)", members.len());
     }
+    cfg.runtime.selected_group = Some(group_name);
+    save_config(&paths, &cfg)?;
+    Ok(())
+}
 
-    let mut candidates: Vec<&ProxyNode> = Vec::new();
-    if let Some(preferred) = cfg.runtime.selected_node.as_deref() {
-        if let Some(node) = supported.iter().copied().find(|n| n.name == preferred) {
-            candidates.push(node);
-        }
-    }
-    let mut remaining: Vec<&ProxyNode> = supported
+/// Resolves a provider group to the members the active core supports.
+fn group_egress<'a>(
+    cfg: &AppConfig,
+    nodes: &'a [ProxyNode],
+    groups: &'a [ProviderGroup],
+    name: &str,
+) -> Result<Egress<'a>> {
+    let group = groups
         .iter()
-        .copied()
-        .filter(|n| {
-            !candidates
-                .iter()
-                .any(|selected| selected.name.as_str() == n.name.as_str())
-        })
+        .find(|g| g.name == name)
+        .with_context(|| format!("Group '{name}' not found. Use `route-cli list-groups`"))?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let members: Vec<&ProxyNode> = group
+        .member_nodes(nodes)
+        .into_iter()
+        .filter(|n| backend.supports(n))
         .collect();
-    remaining.sort_by_key(|n| node_region_priority(&n.name));
-    candidates.extend(remaining);
+    if members.is_empty() {
+        bail!("Group '{name}' has no node supported by {}", backend.name());
+    }
+    Ok(Egress::Group(group, members))
+}
 
-    let mut selected: Option<&ProxyNode> = None;
-    for node in candidates {
-        let host = node.server.as_deref().unwrap_or("");
-        let ok = ping_reachable(host).await;
+pub async fn cmd_render_config(node_name: Option<String>, print: bool) -> Result<()> {
+    let paths = AppPaths::discover()?;
+    let cfg = load_config(&paths)?;
+    let nodes = load_node_pool(&cfg, &paths)?;
+    let groups = load_provider_groups(&cfg, &paths, &nodes)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let egress = match (&node_name, &cfg.runtime.selected_group) {
+        (None, Some(group)) => group_egress(&cfg, &nodes, &groups, group)?,
+        _ => Egress::Node(match (&node_name, &cfg.runtime.selected_node) {
+            (Some(selector), _) => select_known_node(&paths, &nodes, backend, selector)?,
+            (None, Some(name)) => resolve_selected(&cfg.runtime, &nodes)
+                .with_context(|| format!("Node '{name}' not found in cached subscription"))?,
+            (None, None) => nodes.iter().find(|n| backend.supports(n)).context(
+                "No supported node found. Use `route-cli list-nodes` then update subscription.",
+            )?,
+        }),
+    };
+    let label = match &egress {
+        Egress::Node(node) => format!("node '{}'", node.name),
+        Egress::Group(group, _) => format!("group '{}'", group.name),
+    };
+
+    let core_path = resolve_proxy_core_path(&cfg.proxy_core);
+    let core_version = resolve_core_version(&cfg).await?;
+    let config_path = paths.core_config(backend);
+    let chain = Chain::resolve(&cfg, &nodes)?;
+    generate_core_config(&cfg, &egress, &chain, &paths, core_version)?;
+    check_core_config(backend, &core_path, &config_path).await?;
+
+    if print {
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read {}", config_path.display()))?;
+        println!("{content}");
+    } else {
         println!(
-            "[{}] ping {} ({})",
-            if ok { "OK" } else { "FAIL" },
-            node.name,
-            host
+            "[OK] config for {label} passed {} {core_version} check: {}",
+            backend.name(),
+            config_path.display()
         );
-        if ok {
-            selected = Some(node);
-            break;
+    }
+    Ok(())
+}
+
+/// Drops subscriptions whose stored URL cannot be unlocked, with a warning.
+fn unlock_or_skip(subs: &mut Vec<SubscriptionConfig>, secrets: &mut Secrets, skipped: &str) {
+    subs.retain_mut(|sub| match secrets.unlock(sub) {
+        Ok(()) => true,
+        Err(err) => {
+            println!("[WARN] {err:#}; {skipped}");
+            false
         }
+    });
+}
+
+pub async fn cmd_run(command: Vec<String>, node_selector: Option<String>) -> Result<i32> {
+    if command.is_empty() {
+        bail!("No command passed. Example: route-cli run -- claude");
     }
-    let selected = selected.context(
-        "No reachable node after ping checks. Check network/subscription or switch nodes.",
-    )?;
 
-    if cfg.runtime.selected_node.as_deref() != Some(selected.name.as_str()) {
-        cfg.runtime.selected_node = Some(selected.name.clone());
-        save_config(&paths, &cfg)?;
+    let paths = AppPaths::discover()?;
+    let mut cfg = load_config(&paths)?;
+    let mut missing: Vec<SubscriptionConfig> = cfg
+        .subscriptions
+        .iter()
+        .filter(|s| !paths.subscription_cache(&s.name).exists())
+        .cloned()
+        .collect();
+    // Without its URL a subscription can only be used from its cache.
+    unlock_or_skip(
+        &mut missing,
+        &mut Secrets::new(&paths, true),
+        "subscription unavailable",
+    );
+    if !missing.is_empty() {
+        // Offline, carry on with whatever is cached or local.
+        let downloader = if missing.iter().any(|s| s.is_remote()) {
+            connect_downloader(&cfg, &paths)
+                .await
+                .inspect_err(|err| {
+                    println!("[WARN] cannot download missing subscriptions: {err:#}")
+                })
+                .ok()
+        } else {
+            None
+        };
+        for sub in &missing {
+            if sub.is_remote() && downloader.is_none() {
+                continue;
+            }
+            let source = UpdateSource::Fetch(downloader.as_ref().map(|d| &d.client));
+            let result = update_subscription(source, &cfg, sub, &paths, true, false).await;
+            match &result.error {
+                Some(err) => println!("[WARN] subscription '{}' unavailable: {err}", sub.name),
+                None => result.print_table(),
+            }
+        }
+        if let Some(downloader) = downloader {
+            downloader.finish().await?;
+        }
     }
 
-    let core_path = resolve_proxy_core_path(&cfg.proxy_core.path);
+    let nodes = load_node_pool(&cfg, &paths)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let groups = match cfg.runtime.selected_group {
+        Some(_) => load_provider_groups(&cfg, &paths, &nodes)?,
+        None => Vec::new(),
+    };
+
+    let now = unix_now();
+    let mut stale: Vec<SubscriptionConfig> = cfg
+        .subscriptions
+        .iter()
+        .filter(|s| s.is_remote() && paths.subscription_cache(&s.name).exists())
+        .filter(|s| is_cache_stale(&paths, &s.name, cfg.subscription_refresh.max_age_hours, now))
+        .cloned()
+        .collect();
+    // The refresh runs next to the command, so it must not prompt.
+    unlock_or_skip(
+        &mut stale,
+        &mut Secrets::new(&paths, false),
+        "cache not refreshed",
+    );
+    let refresh = (!stale.is_empty()).then(|| {
+        tokio::spawn(refresh_stale_subscriptions(
+            cfg.clone(),
+            stale,
+            paths.clone(),
+        ))
+    });
+
+    let egress = match (node_selector, cfg.runtime.selected_group.clone()) {
+        (Some(selector), _) => {
+            Egress::Node(select_override_node(&cfg, &paths, &nodes, backend, &selector).await?)
+        }
+        (None, Some(group_name)) => {
+            let egress = group_egress(&cfg, &nodes, &groups, &group_name)?;
+            if let Egress::Group(group, members) = &egress {
+                println!(
+                    "[OK] using group '{group_name}' ({}, {} nodes)",
+                    group.kind.clash_name(),
+                    members.len()
+                );
+            }
+            egress
+        }
+        (None, None) => {
+            let supported: Vec<&ProxyNode> = nodes.iter().filter(|n| backend.supports(n)).collect();
+            if supported.is_empty() {
+                bail!(
+                    "No supported node found. Use `route-cli list-nodes` then update subscription."
+                );
+            }
+            let selected = select_reachable_node(&cfg, &paths, &supported).await?;
+            if !cfg.runtime.is_selected(selected) {
+                cfg.runtime.select_node(selected);
+                save_config(&paths, &cfg)?;
+            }
+            Egress::Node(selected)
+        }
+    };
+
+    let core_path = resolve_proxy_core_path(&cfg.proxy_core);
+    let core_version = resolve_core_version(&cfg).await?;
+    let config_path = paths.core_config(backend);
 
-    generate_sing_box_config(&cfg, selected, &paths)?;
-    let mut core = spawn_proxy_core(&core_path, &paths.sing_box_json.to_string_lossy())
+    let chain = Chain::resolve(&cfg, &nodes)?;
+    generate_core_config(&cfg, &egress, &chain, &paths, core_version)?;
+    check_core_config(backend, &core_path, &config_path).await?;
+    let mut core = spawn_proxy_core(backend, &core_path, &config_path)
         .await
         .context("Failed to launch proxy core")?;
 
@@ -532,89 +1560,322 @@ pub async fn cmd_run(command: Vec<String>) -> Result<i32> {
 
     let status = child.wait().await.context("Failed waiting child command")?;
     stop_process(&mut core).await?;
+    if let Some(handle) = refresh {
+        report_background_refresh(handle, &paths).await;
+    }
 
     Ok(status.code().unwrap_or(1))
 }
 
-pub async fn cmd_doctor() -> Result<()> {
+/// `run --node`: the matched node, or the first reachable node of a `region:`
+/// selector. Unlike automatic selection the result is not saved.
+async fn select_override_node<'a>(
+    cfg: &AppConfig,
+    paths: &AppPaths,
+    nodes: &'a [ProxyNode],
+    backend: &dyn CoreBackend,
+    selector: &str,
+) -> Result<&'a ProxyNode> {
+    let matched = select_nodes(nodes, selector)?;
+    if let [node] = matched[..] {
+        ensure_supported(backend, node)?;
+        println!("[OK] using node '{}' for this run", node.name);
+        return Ok(node);
+    }
+    let supported: Vec<&ProxyNode> = matched
+        .into_iter()
+        .filter(|n| backend.supports(n))
+        .collect();
+    if supported.is_empty() {
+        bail!(
+            "No node matching '{selector}' is supported by {}",
+            backend.name()
+        );
+    }
+    select_reachable_node(cfg, paths, &supported).await
+}
+
+/// Pings the last-known-good node first, then the rest by region priority,
+/// and returns the first that answers.
+async fn select_reachable_node<'a>(
+    cfg: &AppConfig,
+    paths: &AppPaths,
+    supported: &[&'a ProxyNode],
+) -> Result<&'a ProxyNode> {
+    let mut candidates: Vec<&ProxyNode> = Vec::new();
+    if let Some(node) = resolve_selected(&cfg.runtime, supported.iter().copied()) {
+        candidates.push(node);
+    }
+    let mut remaining: Vec<&ProxyNode> = supported
+        .iter()
+        .copied()
+        .filter(|n| {
+            !candidates
+                .iter()
+                .any(|selected| selected.name.as_str() == n.name.as_str())
+        })
+        .collect();
+    remaining.sort_by_key(|n| region_priority(&n.name));
+    candidates.extend(remaining);
+
+    // Nodes are only reachable through the upstream proxy, so a direct ping says nothing.
+    if cfg.chain.upstream.is_some() {
+        let node = candidates
+            .first()
+            .copied()
+            .context("No supported node found.")?;
+        println!(
+            "[OK] chain.upstream set; skipping ping checks, using {}",
+            node.name
+        );
+        return Ok(node);
+    }
+
+    let mut selected: Option<&ProxyNode> = None;
+    let mut results = Vec::new();
+    for node in candidates {
+        let host = node.server.as_deref().unwrap_or("");
+        let ms = ping_latency(host).await;
+        match ms {
+            Some(ms) => println!("[OK] ping {} ({}) {} ms", node.name, host, ms),
+            None => println!("[FAIL] ping {} ({})", node.name, host),
+        }
+        results.push((node.fingerprint(), LatencyRecord { ms, at: unix_now() }));
+        if ms.is_some() {
+            selected = Some(node);
+            break;
+        }
+    }
+    if let Err(err) = record_latencies(paths, &results) {
+        println!("[WARN] {err:#}");
+    }
+    selected
+        .context("No reachable node after ping checks. Check network/subscription or switch nodes.")
+}
+
+pub async fn cmd_doctor(output: OutputFormat) -> Result<()> {
     let paths = AppPaths::discover()?;
     let cfg = load_config(&paths)?;
-    let core_path = resolve_proxy_core_path(&cfg.proxy_core.path);
+    let backend = backend_for(cfg.proxy_core.kind);
+    let core_path = resolve_proxy_core_path(&cfg.proxy_core);
+    let mut report = Report::default();
 
-    println!("[OK] config path: {}", paths.config_toml.display());
-    println!(
-        "[{}] subscription URL configured",
-        if cfg.subscription.url.is_some() {
-            "OK"
-        } else {
-            "ERR"
+    report.ok(
+        "config",
+        format!("config path: {}", paths.config_toml.display()),
+    );
+    let local_nodes = load_local_nodes(&cfg, &paths);
+    match &local_nodes {
+        Ok(nodes) if !nodes.is_empty() => {
+            report.ok("local_nodes", format!("local nodes: {}", nodes.len()))
+        }
+        Ok(_) => {}
+        Err(err) => report.err("local_nodes", format!("local nodes: {err:#}")),
+    }
+    if cfg.subscriptions.is_empty() && !local_nodes.is_ok_and(|n| !n.is_empty()) {
+        report.err("sources", "no subscription or local node configured");
+    }
+    for sub in &cfg.subscriptions {
+        let id = format!("subscription.{}", sub.name);
+        let cache = paths.subscription_cache(&sub.name);
+        match read_cached_subscription(&paths, &sub.name).and_then(|raw| parse_subscription(&raw)) {
+            Ok(nodes) => report.ok(
+                format!("{id}.cache"),
+                format!(
+                    "subscription '{}': {} nodes cached at {}",
+                    sub.name,
+                    nodes.len(),
+                    cache.display()
+                ),
+            ),
+            Err(_) if !cache.exists() => report.warn(
+                format!("{id}.cache"),
+                format!(
+                    "subscription '{}': no cache at {}",
+                    sub.name,
+                    cache.display()
+                ),
+            ),
+            Err(err) => report.err(
+                format!("{id}.cache"),
+                format!("subscription '{}': {err:#}", sub.name),
+            ),
         }
+        report_url_storage(&mut report, sub);
+        if cache.exists() {
+            report_subscription_info(&mut report, &cfg, sub, &paths);
+        }
+    }
+    report.ok("proxy_core.kind", format!("proxy core: {}", backend.name()));
+    report.ok(
+        "proxy_core.configured_path",
+        format!("proxy core configured path: {}", cfg.proxy_core.path),
+    );
+    report.ok(
+        "proxy_core.resolved_path",
+        format!("proxy core resolved path: {core_path}"),
     );
-    println!("[OK] proxy core configured path: {}", cfg.proxy_core.path);
-    println!("[OK] proxy core resolved path: {core_path}");
 
     let core_check = Command::new(&core_path)
-        .arg("version")
+        .args(backend.version_args())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status()
         .await;
     match core_check {
-        Ok(status) if status.success() => println!("[OK] proxy core available"),
-        Ok(_) => println!("[WARN] proxy core exists but `version` returned non-zero"),
-        Err(err) => println!("[ERR] proxy core unavailable: {core_path} ({err})"),
+        Ok(status) if status.success() => report.ok("proxy_core.available", "proxy core available"),
+        Ok(_) => report.warn(
+            "proxy_core.available",
+            "proxy core exists but `version` returned non-zero",
+        ),
+        Err(err) => report.err(
+            "proxy_core.available",
+            format!("proxy core unavailable: {core_path} ({err})"),
+        ),
+    }
+    let detected = detect_core_version(backend, &core_path).await;
+    let recorded = cfg.proxy_core.version.as_deref();
+    match (detected.as_deref(), recorded) {
+        (Some(detected), _) => match CoreVersion::parse(detected) {
+            Some(version) if version < backend.minimum_version() => report.err(
+                "proxy_core.version",
+                format!(
+                    "proxy core {detected} is older than the supported {}; run `route-cli upgrade-core`",
+                    backend.minimum_version()
+                ),
+            ),
+            _ => report.ok(
+                "proxy_core.version",
+                format!("proxy core version: {detected}"),
+            ),
+        },
+        (None, Some(recorded)) => report.warn(
+            "proxy_core.version",
+            format!("could not read proxy core version (installed: {recorded})"),
+        ),
+        (None, None) => {}
+    }
+    // A core replaced outside route-cli can differ from what was installed.
+    if let (Some(detected), Some(recorded)) = (detected.as_deref(), recorded) {
+        if detected == recorded {
+            report.ok(
+                "proxy_core.installed_version",
+                "proxy core version matches install",
+            );
+        } else {
+            report.warn(
+                "proxy_core.installed_version",
+                format!("proxy core reports version {detected} but {recorded} was installed"),
+            );
+        }
+    }
+    if let Some(expected) = cfg.proxy_core.sha256.as_deref() {
+        match sha256_file(Path::new(&core_path)) {
+            Ok(actual) if actual == expected => {
+                report.ok("proxy_core.sha256", "proxy core SHA-256 matches install")
+            }
+            Ok(actual) => report.err(
+                "proxy_core.sha256",
+                format!(
+                    "proxy core SHA-256 changed since install: expected {expected}, got {actual}"
+                ),
+            ),
+            Err(err) => report.err(
+                "proxy_core.sha256",
+                format!("proxy core SHA-256 check failed: {err:#}"),
+            ),
+        }
     }
 
-    if paths.subscription_yaml.exists() {
-        let raw = read_cached_subscription(&paths)?;
-        let nodes = parse_subscription(&raw)?;
-        let supported = nodes
-            .iter()
-            .filter(|n| n.is_supported_for_sing_box())
-            .count();
-        println!(
-            "[OK] cached nodes: {} total, {} supported",
-            nodes.len(),
-            supported
+    if let Ok(nodes) = load_node_pool(&cfg, &paths) {
+        let mut reasons: BTreeMap<Unsupported, usize> = BTreeMap::new();
+        for node in &nodes {
+            if let Err(reason) = backend.check(node) {
+                *reasons.entry(reason).or_default() += 1;
+            }
+        }
+        let supported = nodes.len() - reasons.values().sum::<usize>();
+        report.ok(
+            "nodes",
+            format!(
+                "cached nodes: {} total, {} supported",
+                nodes.len(),
+                supported
+            ),
         );
+        for (reason, count) in &reasons {
+            report.warn(
+                "nodes.unsupported",
+                format!("{count} nodes unsupported by {}: {reason}", backend.name()),
+            );
+        }
         if supported == 0 {
-            println!("[WARN] no supported nodes (requires socks5/socks/http/ss/vmess)");
+            report.warn(
+                "nodes.supported",
+                format!("no nodes supported by {}", backend.name()),
+            );
         }
-    } else {
-        println!(
-            "[WARN] no cached subscription: {}",
-            paths.subscription_yaml.display()
-        );
     }
 
-    println!(
-        "[OK] generated config location: {}",
-        paths.sing_box_json.display()
+    report.ok(
+        "generated_config",
+        format!(
+            "generated config location: {}",
+            paths.core_config(backend).display()
+        ),
     );
-    println!("[OK] mixed proxy port: {}", cfg.proxy.mixed_port);
-    Ok(())
+    report.ok(
+        "mixed_port",
+        format!("mixed proxy port: {}", cfg.proxy.mixed_port),
+    );
+
+    match cfg.network.proxy.as_deref() {
+        Some(proxy) => report.ok(
+            "network.proxy",
+            format!("downloads use upstream proxy: {}", redact_url(proxy)),
+        ),
+        None => report.ok("network.proxy", "downloads connect directly"),
+    }
+    if let Some(ca_bundle) = cfg.network.ca_bundle.as_deref() {
+        if Path::new(ca_bundle).exists() {
+            report.ok("network.ca_bundle", format!("CA bundle: {ca_bundle}"));
+        } else {
+            report.err(
+                "network.ca_bundle",
+                format!("CA bundle not found: {ca_bundle}"),
+            );
+        }
+    }
+    if cfg.network.bootstrap_via_node {
+        match cfg.runtime.selected_node.as_deref() {
+            Some(node) => report.ok(
+                "network.bootstrap",
+                format!(
+                    "downloads bootstrap through node '{node}' on port {}",
+                    cfg.network.bootstrap_port
+                ),
+            ),
+            None => report.warn(
+                "network.bootstrap",
+                "bootstrap_via_node is set but no node has been selected yet",
+            ),
+        }
+    }
+    report.print(output)
 }
 
 #[cfg(test)]
 mod tests {
-    use serde_json::json;
-
-    use super::pick_windows_amd64_asset;
+    use super::parse_header_arg;
 
     #[test]
-    fn picks_windows_amd64_asset() {
-        let assets = vec![
-            json!({
-                "name": "sing-box-1.12.20-linux-amd64.tar.gz",
-                "browser_download_url": "https://example.com/linux.tar.gz"
-            }),
-            json!({
-                "name": "sing-box-1.12.20-windows-amd64.zip",
-                "browser_download_url": "https://example.com/windows.zip"
-            }),
-        ];
-        let selected = pick_windows_amd64_asset(&assets).expect("should pick windows asset");
-        assert_eq!(selected.0, "sing-box-1.12.20-windows-amd64.zip");
-        assert_eq!(selected.1, "https://example.com/windows.zip");
+    fn parses_header_args() {
+        let (name, value) =
+            parse_header_arg("Authorization: Bearer abc:def").expect("header should parse");
+        assert_eq!(name, "Authorization");
+        assert_eq!(value, "Bearer abc:def");
+
+        assert!(parse_header_arg("no-colon").is_err());
+        assert!(parse_header_arg("bad name: x").is_err());
     }
 }
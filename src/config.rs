@@ -1,11 +1,17 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
+use crate::backend::{CoreBackend, backend_for, is_default_core_name};
+use crate::subscription::ProxyNode;
+
 const APP_DIR: &str = "route";
 const LEGACY_APP_DIR: &str = "codex-route";
+pub const DEFAULT_SUBSCRIPTION_NAME: &str = "default";
 const DEFAULT_PROXY_DOMAINS: [&str; 6] = [
     "openai.com",
     "api.openai.com",
@@ -17,21 +23,145 @@ const DEFAULT_PROXY_DOMAINS: [&str; 6] = [
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
-    pub subscription: SubscriptionConfig,
+    #[serde(default, skip_serializing)]
+    subscription: Option<LegacySubscriptionConfig>,
+    #[serde(default)]
+    pub subscriptions: Vec<SubscriptionConfig>,
     pub proxy_core: ProxyCoreConfig,
     pub proxy: LocalProxyConfig,
     pub routing: RoutingConfig,
     pub runtime: RuntimeConfig,
+    #[serde(default)]
+    pub subscription_alerts: SubscriptionAlertConfig,
+    #[serde(default)]
+    pub subscription_refresh: SubscriptionRefreshConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub chain: ChainConfig,
+    /// User-defined nodes, in the Clash proxy schema.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<ProxyNode>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubscriptionConfig {
+    pub name: String,
+    /// `http(s)://` or `file://` source; unset for subscriptions imported
+    /// from stdin, which are only refreshed by `update --from`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    /// Where the URL is kept instead of `url` when it carries a secret token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_store: Option<SecretStore>,
+    /// Overrides the User-Agent implied by `format`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub format: SubscriptionFormat,
+    /// Extra request headers, e.g. provider auth tokens. Kept in the secret
+    /// store together with the URL once `url_store` is set.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl SubscriptionConfig {
+    pub fn new(name: &str, url: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            url,
+            url_store: None,
+            user_agent: None,
+            format: SubscriptionFormat::default(),
+            headers: BTreeMap::new(),
+        }
+    }
+
+    pub fn effective_user_agent(&self) -> &str {
+        self.user_agent
+            .as_deref()
+            .unwrap_or(self.format.default_user_agent())
+    }
+
+    /// Whether updating needs the network, i.e. the URL is `http(s)://`.
+    /// Only such URLs are moved to a secret store.
+    pub fn is_remote(&self) -> bool {
+        self.url_store.is_some()
+            || self
+                .url
+                .as_deref()
+                .is_some_and(|url| url.starts_with("http://") || url.starts_with("https://"))
+    }
+
+    /// Local path of a `file://` subscription.
+    pub fn file_path(&self) -> Option<PathBuf> {
+        let url = reqwest::Url::parse(self.url.as_deref()?).ok()?;
+        (url.scheme() == "file")
+            .then(|| url.to_file_path().ok())
+            .flatten()
+    }
+}
+
+/// Subscription flavour requested from the provider. Providers pick the
+/// response format from the User-Agent, so this selects the default one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SubscriptionFormat {
+    #[default]
+    Clash,
+    ClashMeta,
+}
+
+impl SubscriptionFormat {
+    pub fn default_user_agent(self) -> &'static str {
+        match self {
+            Self::Clash => "clash",
+            Self::ClashMeta => "clash.meta",
+        }
+    }
+}
+
+/// Where a subscription URL is kept outside `config.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecretStore {
+    /// macOS Keychain, Windows Credential Manager or the Secret Service.
+    Keyring,
+    /// `secrets.json`, encrypted with a passphrase.
+    File,
+}
+
+/// Single-subscription layout used before `[[subscriptions]]` existed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LegacySubscriptionConfig {
+    url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyCoreConfig {
+    #[serde(default)]
+    pub kind: CoreKind,
     pub path: String,
+    /// SHA-256 of the binary installed by `install-core`, re-checked by `doctor`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Version installed by `install-core`/`upgrade-core`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Version that was active before the last install, used by `rollback-core`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_version: Option<String>,
+}
+
+/// Proxy core that runs the generated config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoreKind {
+    #[default]
+    SingBox,
+    /// mihomo (Clash.Meta)
+    Mihomo,
+    Xray,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,19 +173,129 @@ pub struct LocalProxyConfig {
 pub struct RoutingConfig {
     pub proxy_domains: Vec<String>,
     pub no_proxy: Vec<String>,
+    /// Also route what the selected provider group's own rules send to it.
+    #[serde(default)]
+    pub provider_rules: bool,
+    /// Remote sing-box rule sets (`.srs` or `.json`) routed through the proxy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_sets: Vec<String>,
+    /// DNS server sing-box resolves with, dialed through the proxy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuntimeConfig {
     pub selected_node: Option<String>,
+    /// `ProxyNode::fingerprint` of `selected_node`, used to follow renames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected_fingerprint: Option<String>,
+    /// Provider proxy group `run` uses instead of a single node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected_group: Option<String>,
+}
+
+impl RuntimeConfig {
+    pub fn select_node(&mut self, node: &ProxyNode) {
+        self.selected_node = Some(node.name.clone());
+        self.selected_fingerprint = Some(node.fingerprint());
+    }
+
+    pub fn clear_node(&mut self) {
+        self.selected_node = None;
+        self.selected_fingerprint = None;
+    }
+
+    /// Whether `node` is the saved selection under its current name.
+    pub fn is_selected(&self, node: &ProxyNode) -> bool {
+        self.selected_node.as_deref() == Some(node.name.as_str())
+            && self.selected_fingerprint.as_deref() == Some(node.fingerprint().as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubscriptionAlertConfig {
+    /// Warn when less than this percentage of the traffic quota is left.
+    pub min_remaining_percent: u8,
+    /// Warn when the subscription expires within this many days.
+    pub expiry_warn_days: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubscriptionRefreshConfig {
+    /// Caches older than this are refreshed in the background by `run`.
+    /// `0` disables automatic refresh.
+    pub max_age_hours: u64,
+}
+
+/// Settings for route-cli's own downloads (subscriptions and sing-box
+/// releases); they do not affect the proxied target command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Upstream proxy URL, e.g. `http://proxy.corp.example:8080`.
+    pub proxy: Option<String>,
+    /// PEM file with extra root certificates, e.g. a corporate TLS-inspection CA.
+    pub ca_bundle: Option<String>,
+    /// Connect and read timeout in seconds.
+    pub timeout_secs: u64,
+    /// Download through the last-known-good node via a temporary proxy core.
+    pub bootstrap_via_node: bool,
+    /// Local port of the temporary bootstrap proxy core.
+    pub bootstrap_port: u16,
+}
+
+/// Proxies the selected node or group is dialed through, in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChainConfig {
+    /// `http://` or `socks5://` proxy dialed first, e.g. a corporate proxy
+    /// that is the only allowed egress.
+    pub upstream: Option<String>,
+    /// Subscription nodes dialed after `upstream`, first to last.
+    pub hops: Vec<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            ca_bundle: None,
+            timeout_secs: 30,
+            bootstrap_via_node: false,
+            bootstrap_port: 27891,
+        }
+    }
+}
+
+impl Default for SubscriptionRefreshConfig {
+    fn default() -> Self {
+        Self { max_age_hours: 24 }
+    }
+}
+
+impl Default for SubscriptionAlertConfig {
+    fn default() -> Self {
+        Self {
+            min_remaining_percent: 10,
+            expiry_warn_days: 7,
+        }
+    }
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
-            subscription: SubscriptionConfig { url: None },
+            subscription: None,
+            subscriptions: Vec::new(),
             proxy_core: ProxyCoreConfig {
-                path: "sing-box.exe".to_string(),
+                kind: CoreKind::default(),
+                path: backend_for(CoreKind::default()).binary_name().to_string(),
+                sha256: None,
+                version: None,
+                previous_version: None,
             },
             proxy: LocalProxyConfig { mixed_port: 27890 },
             routing: RoutingConfig {
@@ -64,20 +304,110 @@ impl Default for AppConfig {
                     .map(|s| s.to_string())
                     .collect(),
                 no_proxy: vec!["localhost".to_string(), "127.0.0.1".to_string()],
+                provider_rules: false,
+                rule_sets: Vec::new(),
+                dns: None,
             },
             runtime: RuntimeConfig {
                 selected_node: None,
+                selected_fingerprint: None,
+                selected_group: None,
             },
+            subscription_alerts: SubscriptionAlertConfig::default(),
+            subscription_refresh: SubscriptionRefreshConfig::default(),
+            network: NetworkConfig::default(),
+            chain: ChainConfig::default(),
+            nodes: Vec::new(),
         }
     }
 }
 
+impl AppConfig {
+    pub fn find_subscription(&self, name: &str) -> Option<&SubscriptionConfig> {
+        self.subscriptions.iter().find(|s| s.name == name)
+    }
+
+    pub fn upsert_subscription(&mut self, sub: SubscriptionConfig) {
+        match self.subscriptions.iter_mut().find(|s| s.name == sub.name) {
+            Some(existing) => *existing = sub,
+            None => self.subscriptions.push(sub),
+        }
+    }
+
+    pub fn remove_subscription(&mut self, name: &str) -> bool {
+        let before = self.subscriptions.len();
+        self.subscriptions.retain(|s| s.name != name);
+        self.subscriptions.len() != before
+    }
+
+    /// Adds a node to `[[nodes]]`, replacing one with the same name. Returns
+    /// whether a node was replaced.
+    pub fn upsert_node(&mut self, node: ProxyNode) -> bool {
+        match self.nodes.iter_mut().find(|n| n.name == node.name) {
+            Some(existing) => {
+                *existing = node;
+                true
+            }
+            None => {
+                self.nodes.push(node);
+                false
+            }
+        }
+    }
+
+    pub fn remove_node(&mut self, name: &str) -> bool {
+        let before = self.nodes.len();
+        self.nodes.retain(|n| n.name != name);
+        self.nodes.len() != before
+    }
+}
+
+pub fn validate_subscription_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Invalid subscription name '{name}': use letters, digits, '-' or '_'");
+    }
+    Ok(())
+}
+
+/// Accepts `http://`, `https://` and `file://` subscription URLs.
+pub fn validate_subscription_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).context("Invalid subscription URL")?;
+    if !matches!(parsed.scheme(), "http" | "https" | "file") {
+        bail!(
+            "Unsupported subscription URL scheme '{}' (use http, https or file)",
+            parsed.scheme()
+        );
+    }
+    Ok(())
+}
+
+/// `file://` URL of a local subscription file.
+pub fn file_subscription_url(path: &str) -> Result<String> {
+    let path =
+        fs::canonicalize(path).with_context(|| format!("Subscription file {path} not found"))?;
+    reqwest::Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| anyhow::anyhow!("Cannot use {} as a file URL", path.display()))
+}
+
 #[derive(Debug, Clone)]
 pub struct AppPaths {
     pub config_toml: PathBuf,
-    pub subscription_yaml: PathBuf,
+    pub legacy_subscription_yaml: PathBuf,
+    pub subscriptions_dir: PathBuf,
+    /// Installed proxy cores, as `<core>/<version>/`.
+    pub bin_dir: PathBuf,
     pub generated_dir: PathBuf,
-    pub sing_box_json: PathBuf,
+    /// Self-hosted nodes in Clash `proxies:` format, merged ahead of subscriptions.
+    pub nodes_yaml: PathBuf,
+    /// Last ping result per node, written by `run`.
+    pub latency_json: PathBuf,
+    /// Subscription URLs encrypted with `url_store = "file"`.
+    pub secrets_json: PathBuf,
 }
 
 impl AppPaths {
@@ -85,23 +415,61 @@ impl AppPaths {
         let appdata = dirs::config_dir().context("Unable to locate config directory")?;
         let root = appdata.join(APP_DIR);
         migrate_legacy_root(&appdata, &root)?;
-        let config_toml = root.join("config.toml");
-        let subscription_yaml = root.join("cache").join("subscription.yaml");
-        let generated_dir = root.join("generated");
-        let sing_box_json = generated_dir.join("sing-box.json");
-        Ok(Self {
-            config_toml,
-            subscription_yaml,
-            generated_dir,
-            sing_box_json,
-        })
+        Ok(Self::in_root(&root))
+    }
+
+    /// Layout under an app directory such as `~/.config/route`.
+    pub fn in_root(root: &Path) -> Self {
+        Self {
+            config_toml: root.join("config.toml"),
+            legacy_subscription_yaml: root.join("cache").join("subscription.yaml"),
+            subscriptions_dir: root.join("cache").join("subscriptions"),
+            bin_dir: root.join("bin"),
+            generated_dir: root.join("generated"),
+            nodes_yaml: root.join("nodes.yaml"),
+            latency_json: root.join("cache").join("latency.json"),
+            secrets_json: root.join("secrets.json"),
+        }
+    }
+
+    /// Versions of one core, e.g. `bin/sing-box/`.
+    pub fn core_bin_dir(&self, backend: &dyn CoreBackend) -> PathBuf {
+        self.bin_dir.join(backend.name())
+    }
+
+    /// Config generated for `run`, e.g. `generated/sing-box.json`.
+    pub fn core_config(&self, backend: &dyn CoreBackend) -> PathBuf {
+        self.generated_dir
+            .join(format!("{}.{}", backend.name(), backend.config_extension()))
+    }
+
+    pub fn bootstrap_config(&self, backend: &dyn CoreBackend) -> PathBuf {
+        self.generated_dir
+            .join(format!("bootstrap.{}", backend.config_extension()))
+    }
+
+    pub fn subscription_cache(&self, name: &str) -> PathBuf {
+        self.subscriptions_dir.join(format!("{name}.yaml"))
+    }
+
+    /// Cache replaced by the last `update`.
+    pub fn subscription_backup(&self, name: &str) -> PathBuf {
+        self.subscriptions_dir.join(format!("{name}.yaml.bak"))
+    }
+
+    pub fn subscription_meta(&self, name: &str) -> PathBuf {
+        self.subscriptions_dir.join(format!("{name}.meta.json"))
     }
 
     pub fn ensure_dirs(&self) -> Result<()> {
         ensure_parent(&self.config_toml)?;
-        ensure_parent(&self.subscription_yaml)?;
-        fs::create_dir_all(&self.generated_dir)
-            .with_context(|| format!("Failed to create {}", self.generated_dir.display()))?;
+        if let Some(root) = self.config_toml.parent() {
+            restrict_permissions(root, 0o700)?;
+        }
+        for dir in [&self.subscriptions_dir, &self.generated_dir] {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
         Ok(())
     }
 }
@@ -169,21 +537,104 @@ pub fn load_config(paths: &AppPaths) -> Result<AppConfig> {
     }
     let raw = fs::read_to_string(&paths.config_toml)
         .with_context(|| format!("Failed to read {}", paths.config_toml.display()))?;
-    let cfg = toml::from_str::<AppConfig>(&raw).context("Invalid config.toml format")?;
+    let mut cfg = toml::from_str::<AppConfig>(&raw).context("Invalid config.toml format")?;
+    if migrate_legacy_subscription(paths, &mut cfg)? {
+        save_config(paths, &cfg)?;
+    }
     Ok(cfg)
 }
 
+/// Moves a pre-`[[subscriptions]]` `subscription.url` and its cache into the
+/// default named subscription. Returns whether the config changed.
+fn migrate_legacy_subscription(paths: &AppPaths, cfg: &mut AppConfig) -> Result<bool> {
+    let Some(legacy) = cfg.subscription.take() else {
+        return Ok(false);
+    };
+    let Some(url) = legacy.url else {
+        return Ok(true);
+    };
+    if cfg.find_subscription(DEFAULT_SUBSCRIPTION_NAME).is_some() {
+        return Ok(true);
+    }
+    cfg.upsert_subscription(SubscriptionConfig::new(
+        DEFAULT_SUBSCRIPTION_NAME,
+        Some(url),
+    ));
+
+    let new_cache = paths.subscription_cache(DEFAULT_SUBSCRIPTION_NAME);
+    if paths.legacy_subscription_yaml.exists() && !new_cache.exists() {
+        paths.ensure_dirs()?;
+        fs::rename(&paths.legacy_subscription_yaml, &new_cache).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                paths.legacy_subscription_yaml.display(),
+                new_cache.display()
+            )
+        })?;
+    }
+    Ok(true)
+}
+
 pub fn save_config(paths: &AppPaths, cfg: &AppConfig) -> Result<()> {
     paths.ensure_dirs()?;
-    let content = toml::to_string_pretty(cfg).context("Failed to serialize config")?;
-    fs::write(&paths.config_toml, content)
-        .with_context(|| format!("Failed to write {}", paths.config_toml.display()))?;
+    // Stored URLs are filled in memory for the current command only.
+    let mut cfg = cfg.clone();
+    for sub in &mut cfg.subscriptions {
+        if sub.url_store.is_some() {
+            sub.url = None;
+        }
+    }
+    let content = toml::to_string_pretty(&cfg).context("Failed to serialize config")?;
+    write_private(&paths.config_toml, content)
+}
+
+/// Writes a file only the current user can read: config, caches holding node
+/// passwords and generated core configs. On Windows the per-user profile
+/// directory already provides this.
+pub fn write_private(path: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    // `mode` only applies to new files; an existing one is restricted
+    // before anything is written to it.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict permissions of {}", path.display()))?;
+    }
+    file.write_all(content.as_ref())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(unix)]
+pub fn restrict_permissions(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to restrict permissions of {}", path.display()))
+}
+
+#[cfg(not(unix))]
+pub fn restrict_permissions(_path: &Path, _mode: u32) -> Result<()> {
     Ok(())
 }
 
-pub fn resolve_proxy_core_path(configured_path: &str) -> String {
-    let bundled_rel = PathBuf::from("tools/sing-box/sing-box.exe");
-    if configured_path.eq_ignore_ascii_case("sing-box.exe") {
+pub fn resolve_proxy_core_path(core: &ProxyCoreConfig) -> String {
+    let backend = backend_for(core.kind);
+    // A bare name of another core is left over from switching `kind`.
+    let configured_path = if is_default_core_name(&core.path) {
+        backend.binary_name()
+    } else {
+        core.path.as_str()
+    };
+    let bundled_rel = PathBuf::from("tools")
+        .join(backend.name())
+        .join(backend.binary_name());
+    if is_default_core_name(configured_path) {
         if bundled_rel.exists() {
             return bundled_rel.to_string_lossy().into_owned();
         }
@@ -193,12 +644,12 @@ pub fn resolve_proxy_core_path(configured_path: &str) -> String {
                 return candidate.to_string_lossy().into_owned();
             }
         }
-        if let Ok(exe) = std::env::current_exe() {
-            if let Some(exe_dir) = exe.parent() {
-                let candidate = exe_dir.join(&bundled_rel);
-                if candidate.exists() {
-                    return candidate.to_string_lossy().into_owned();
-                }
+        if let Ok(exe) = std::env::current_exe()
+            && let Some(exe_dir) = exe.parent()
+        {
+            let candidate = exe_dir.join(&bundled_rel);
+            if candidate.exists() {
+                return candidate.to_string_lossy().into_owned();
             }
         }
     }
@@ -219,17 +670,20 @@ pub fn resolve_proxy_core_path(configured_path: &str) -> String {
         }
     }
 
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(exe_dir) = exe.parent() {
-            let candidate = exe_dir.join(&configured);
-            if candidate.exists() {
-                return candidate.to_string_lossy().into_owned();
-            }
+    if let Ok(exe) = std::env::current_exe()
+        && let Some(exe_dir) = exe.parent()
+    {
+        let candidate = exe_dir.join(&configured);
+        if candidate.exists() {
+            return candidate.to_string_lossy().into_owned();
         }
     }
 
-    if configured_path.eq_ignore_ascii_case("tools/sing-box/sing-box.exe") {
-        return "sing-box.exe".to_string();
+    let bundled_default = Path::new(configured_path)
+        .strip_prefix(Path::new("tools").join(backend.name()))
+        .is_ok_and(|rest| is_default_core_name(&rest.to_string_lossy()));
+    if is_default_core_name(configured_path) || bundled_default {
+        return backend.binary_name().to_string();
     }
 
     configured_path.to_string()
@@ -237,19 +691,29 @@ pub fn resolve_proxy_core_path(configured_path: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use super::{
+        AppConfig, AppPaths, DEFAULT_SUBSCRIPTION_NAME, SecretStore, SubscriptionConfig,
+        copy_dir_recursive, load_config, migrate_legacy_root, migrate_legacy_subscription,
+        save_config,
+    };
+    use crate::test_util::make_temp_dir;
 
-    use super::{copy_dir_recursive, migrate_legacy_root};
+    const LEGACY_CONFIG: &str = r#"
+[subscription]
+url = "https://example.com/sub"
 
-    fn make_temp_dir(prefix: &str) -> std::path::PathBuf {
-        let stamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("time should be valid")
-            .as_nanos();
-        let path = std::env::temp_dir().join(format!("{prefix}-{}-{stamp}", std::process::id()));
-        std::fs::create_dir_all(&path).expect("temp dir should be creatable");
-        path
-    }
+[proxy_core]
+path = "sing-box.exe"
+
+[proxy]
+mixed_port = 27890
+
+[routing]
+proxy_domains = ["openai.com"]
+no_proxy = ["localhost"]
+
+[runtime]
+"#;
 
     #[test]
     fn migrates_legacy_config_when_new_root_missing() {
@@ -309,4 +773,81 @@ mod tests {
             "ok"
         );
     }
+
+    #[test]
+    fn migrates_single_subscription_to_named_list() {
+        let root = make_temp_dir("route-cli-legacy-sub");
+        let paths = AppPaths::in_root(&root);
+        std::fs::create_dir_all(root.join("cache")).expect("cache dir should be created");
+        std::fs::write(&paths.legacy_subscription_yaml, "proxies: []")
+            .expect("legacy cache should be written");
+        let mut cfg: AppConfig = toml::from_str(LEGACY_CONFIG).expect("legacy config should parse");
+
+        assert!(migrate_legacy_subscription(&paths, &mut cfg).expect("migration should succeed"));
+
+        let sub = cfg
+            .find_subscription(DEFAULT_SUBSCRIPTION_NAME)
+            .expect("default subscription should exist");
+        assert_eq!(sub.url.as_deref(), Some("https://example.com/sub"));
+        assert!(sub.is_remote());
+        assert!(paths.subscription_cache(DEFAULT_SUBSCRIPTION_NAME).exists());
+        assert!(!paths.legacy_subscription_yaml.exists());
+        let saved = toml::to_string(&cfg).expect("migrated config should serialize");
+        assert!(saved.contains("[[subscriptions]]"));
+        assert!(!saved.contains("[subscription]"));
+    }
+
+    #[test]
+    fn local_nodes_round_trip_through_toml() {
+        let mut cfg = AppConfig::default();
+        let node = serde_yaml::from_str(
+            "{ name: office, type: wireguard, server: wg.example, port: 51820, ip: 10.0.0.2, reserved: [1, 2, 3] }",
+        )
+        .expect("valid node");
+        assert!(!cfg.upsert_node(node));
+
+        let saved = toml::to_string(&cfg).expect("config should serialize");
+        assert!(saved.contains("[[nodes]]"));
+        let mut loaded: AppConfig = toml::from_str(&saved).expect("config should parse");
+        assert_eq!(loaded.nodes[0].node_type, "wireguard");
+        assert_eq!(loaded.nodes[0].extra_str("ip"), Some("10.0.0.2"));
+        assert!(loaded.remove_node("office"));
+        assert!(loaded.nodes.is_empty());
+    }
+
+    #[test]
+    fn saves_stored_urls_out_of_config_and_privately() {
+        let root = make_temp_dir("route-cli-secret-url");
+        let paths = AppPaths::in_root(&root);
+        let mut cfg = AppConfig::default();
+        let mut sub =
+            SubscriptionConfig::new("work", Some("https://sub.example/api?token=s3cret".into()));
+        sub.url_store = Some(SecretStore::Keyring);
+        cfg.upsert_subscription(sub);
+        // An existing world-readable config is tightened on save.
+        std::fs::write(&paths.config_toml, "").expect("config should be created");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&paths.config_toml, std::fs::Permissions::from_mode(0o644))
+                .unwrap();
+        }
+
+        save_config(&paths, &cfg).expect("config should save");
+
+        let saved = std::fs::read_to_string(&paths.config_toml).expect("config should exist");
+        assert!(!saved.contains("s3cret"), "{saved}");
+        assert!(saved.contains("url_store = \"keyring\""), "{saved}");
+        let loaded = load_config(&paths).expect("config should load");
+        assert!(loaded.subscriptions[0].is_remote());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&paths.config_toml)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
 }
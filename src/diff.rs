@@ -0,0 +1,136 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::subscription::ProxyNode;
+
+/// A node present before and after an update whose settings differ.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeChange {
+    pub name: String,
+    /// Set when the provider renamed the node.
+    pub previous_name: Option<String>,
+    /// Clash keys whose values changed, e.g. `server` or `ws-opts`.
+    pub fields: Vec<String>,
+}
+
+/// Difference between the previous and the new cache of one subscription.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct NodeDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<NodeChange>,
+}
+
+impl NodeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
+
+/// Pairs nodes by fingerprint, so renames count as changes, then by name, so
+/// a node moved to a new server is a change rather than a removal plus an
+/// addition.
+pub fn diff_nodes(previous: &[ProxyNode], current: &[ProxyNode]) -> NodeDiff {
+    let mut unmatched: Vec<Option<&ProxyNode>> = previous.iter().map(Some).collect();
+    let mut take = |matches: &dyn Fn(&ProxyNode) -> bool| {
+        unmatched
+            .iter_mut()
+            .find(|slot| slot.is_some_and(matches))
+            .and_then(Option::take)
+    };
+
+    let mut diff = NodeDiff::default();
+    for node in current {
+        let fingerprint = node.fingerprint();
+        let old = take(&|old| old.fingerprint() == fingerprint)
+            .or_else(|| take(&|old| old.name == node.name));
+        let Some(old) = old else {
+            diff.added.push(node.name.clone());
+            continue;
+        };
+        let fields = changed_fields(old, node);
+        if !fields.is_empty() {
+            diff.changed.push(NodeChange {
+                name: node.name.clone(),
+                previous_name: (old.name != node.name).then(|| old.name.clone()),
+                fields,
+            });
+        }
+    }
+    diff.removed = unmatched
+        .into_iter()
+        .flatten()
+        .map(|n| n.name.clone())
+        .collect();
+    diff
+}
+
+fn changed_fields(old: &ProxyNode, new: &ProxyNode) -> Vec<String> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| {
+            old.get(*key).unwrap_or(&Value::Null) != new.get(*key).unwrap_or(&Value::Null)
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NodeChange, diff_nodes};
+    use crate::subscription::ProxyNode;
+
+    fn nodes(yaml: &str) -> Vec<ProxyNode> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn reports_added_removed_renamed_and_moved_nodes() {
+        let previous = nodes(
+            "[{ name: SG 01, type: ss, server: sg.example, port: 8388, password: a },
+              { name: HK 01, type: ss, server: hk1.example, port: 8388, password: b },
+              { name: US 01, type: ss, server: us.example, port: 8388, password: c }]",
+        );
+        let current = nodes(
+            "[{ name: SG-01 x1.0, type: ss, server: sg.example, port: 8388, password: a },
+              { name: HK 01, type: ss, server: hk2.example, port: 8388, password: b },
+              { name: JP 01, type: ss, server: jp.example, port: 8388, password: d }]",
+        );
+
+        let diff = diff_nodes(&previous, &current);
+        assert_eq!(diff.added, ["JP 01"]);
+        assert_eq!(diff.removed, ["US 01"]);
+        assert_eq!(
+            diff.changed,
+            [
+                NodeChange {
+                    name: "SG-01 x1.0".to_string(),
+                    previous_name: Some("SG 01".to_string()),
+                    fields: vec!["name".to_string()],
+                },
+                NodeChange {
+                    name: "HK 01".to_string(),
+                    previous_name: None,
+                    fields: vec!["server".to_string()],
+                },
+            ]
+        );
+        assert!(diff_nodes(&current, &current).is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use serde_yaml::Value;
+
+use crate::config::{AppConfig, AppPaths};
+use crate::subscription::{
+    ClashSubscription, ProxyNode, is_local_node, prefixes_source_names, read_cached_subscription,
+};
+
+/// Clash `proxy-groups` types route-cli can reproduce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupKind {
+    Select,
+    UrlTest,
+    Fallback,
+    LoadBalance,
+}
+
+impl GroupKind {
+    fn from_clash(name: &str) -> Option<Self> {
+        match name {
+            "select" => Some(Self::Select),
+            "url-test" => Some(Self::UrlTest),
+            "fallback" => Some(Self::Fallback),
+            "load-balance" => Some(Self::LoadBalance),
+            _ => None,
+        }
+    }
+
+    pub fn clash_name(self) -> &'static str {
+        match self {
+            Self::Select => "select",
+            Self::UrlTest => "url-test",
+            Self::Fallback => "fallback",
+            Self::LoadBalance => "load-balance",
+        }
+    }
+}
+
+/// Clash rule types that have a direct equivalent in every core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleMatch {
+    Domain,
+    DomainSuffix,
+    DomainKeyword,
+    IpCidr,
+}
+
+impl RuleMatch {
+    fn from_clash(name: &str) -> Option<Self> {
+        match name {
+            "DOMAIN" => Some(Self::Domain),
+            "DOMAIN-SUFFIX" => Some(Self::DomainSuffix),
+            "DOMAIN-KEYWORD" => Some(Self::DomainKeyword),
+            "IP-CIDR" | "IP-CIDR6" => Some(Self::IpCidr),
+            _ => None,
+        }
+    }
+
+    pub fn clash_name(self) -> &'static str {
+        match self {
+            Self::Domain => "DOMAIN",
+            Self::DomainSuffix => "DOMAIN-SUFFIX",
+            Self::DomainKeyword => "DOMAIN-KEYWORD",
+            Self::IpCidr => "IP-CIDR",
+        }
+    }
+}
+
+/// A provider rule that sends matching traffic to its group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderRule {
+    pub kind: RuleMatch,
+    pub value: String,
+}
+
+/// A provider proxy group with members resolved to node names in the pool.
+#[derive(Debug, Clone)]
+pub struct ProviderGroup {
+    /// Prefixed with the subscription name like nodes when several are configured.
+    pub name: String,
+    pub kind: GroupKind,
+    pub members: Vec<String>,
+    pub url: Option<String>,
+    /// Seconds between health checks.
+    pub interval: Option<u64>,
+    /// Milliseconds of latency difference before `url-test` switches.
+    pub tolerance: Option<u64>,
+    pub rules: Vec<ProviderRule>,
+    /// Rules targeting this group whose type has no equivalent (GEOIP, RULE-SET, ...).
+    pub skipped_rules: usize,
+}
+
+impl ProviderGroup {
+    pub fn member_nodes<'a>(&self, pool: &'a [ProxyNode]) -> Vec<&'a ProxyNode> {
+        self.members
+            .iter()
+            .filter_map(|name| pool.iter().find(|n| &n.name == name))
+            .collect()
+    }
+}
+
+/// Loads the proxy groups of every cached subscription. `pool` is the merged
+/// node pool, used to map members to the names nodes were given after
+/// prefixing and de-duplication.
+pub fn load_provider_groups(
+    cfg: &AppConfig,
+    paths: &AppPaths,
+    pool: &[ProxyNode],
+) -> Result<Vec<ProviderGroup>> {
+    let pool_names: HashMap<String, &str> = pool
+        .iter()
+        .map(|n| (n.fingerprint(), n.name.as_str()))
+        .collect();
+    let prefix_names = prefixes_source_names(cfg, pool.iter().any(is_local_node));
+
+    let mut groups = Vec::new();
+    for sub in &cfg.subscriptions {
+        if !paths.subscription_cache(&sub.name).exists() {
+            continue;
+        }
+        let raw = read_cached_subscription(paths, &sub.name)?;
+        let parsed: ClashSubscription = serde_yaml::from_str(&raw)
+            .with_context(|| format!("Invalid cache for subscription '{}'", sub.name))?;
+        let prefix = prefix_names.then_some(sub.name.as_str());
+        groups.extend(resolve_groups(&parsed, &pool_names, prefix));
+    }
+    Ok(groups)
+}
+
+struct RawGroup {
+    name: String,
+    kind: GroupKind,
+    proxies: Vec<String>,
+    url: Option<String>,
+    interval: Option<u64>,
+    tolerance: Option<u64>,
+}
+
+/// Reads one `proxy-groups` entry leniently; providers vary in which keys
+/// they set and whether numbers are quoted.
+fn parse_raw_group(value: &Value) -> Option<RawGroup> {
+    let number = |key: &str| match value.get(key)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    let proxies = value
+        .get("proxies")
+        .and_then(Value::as_sequence)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(RawGroup {
+        name: value.get("name")?.as_str()?.to_string(),
+        kind: GroupKind::from_clash(value.get("type")?.as_str()?)?,
+        proxies,
+        url: value.get("url").and_then(Value::as_str).map(str::to_string),
+        interval: number("interval"),
+        tolerance: number("tolerance"),
+    })
+}
+
+fn resolve_groups(
+    parsed: &ClashSubscription,
+    pool_names: &HashMap<String, &str>,
+    prefix: Option<&str>,
+) -> Vec<ProviderGroup> {
+    let raw_groups: Vec<RawGroup> = parsed
+        .proxy_groups
+        .iter()
+        .filter_map(parse_raw_group)
+        .collect();
+    let by_name: HashMap<&str, &RawGroup> =
+        raw_groups.iter().map(|g| (g.name.as_str(), g)).collect();
+    let node_keys: HashMap<&str, String> = parsed
+        .proxies
+        .iter()
+        .flatten()
+        .map(|n| (n.name.as_str(), n.fingerprint()))
+        .collect();
+
+    raw_groups
+        .iter()
+        .map(|group| {
+            let mut members = Vec::new();
+            let mut visited = HashSet::from([group.name.as_str()]);
+            collect_members(
+                group,
+                &by_name,
+                &node_keys,
+                pool_names,
+                &mut visited,
+                &mut members,
+            );
+
+            let mut rules = Vec::new();
+            let mut skipped_rules = 0;
+            for line in &parsed.rules {
+                let parts: Vec<&str> = line.split(',').map(str::trim).collect();
+                if parts.len() < 3 || parts[2] != group.name {
+                    continue;
+                }
+                match RuleMatch::from_clash(parts[0]) {
+                    Some(kind) => rules.push(ProviderRule {
+                        kind,
+                        value: parts[1].to_string(),
+                    }),
+                    None => skipped_rules += 1,
+                }
+            }
+
+            ProviderGroup {
+                name: match prefix {
+                    Some(source) => format!("{source}/{}", group.name),
+                    None => group.name.clone(),
+                },
+                kind: group.kind,
+                members,
+                url: group.url.clone(),
+                interval: group.interval,
+                tolerance: group.tolerance,
+                rules,
+                skipped_rules,
+            }
+        })
+        .collect()
+}
+
+/// Flattens nested groups into node names, skipping `DIRECT`, `REJECT` and
+/// proxy providers, which have no node in the pool.
+fn collect_members<'a>(
+    group: &'a RawGroup,
+    by_name: &HashMap<&str, &'a RawGroup>,
+    node_keys: &HashMap<&str, String>,
+    pool_names: &HashMap<String, &str>,
+    visited: &mut HashSet<&'a str>,
+    members: &mut Vec<String>,
+) {
+    for member in &group.proxies {
+        if let Some(pool_name) = node_keys
+            .get(member.as_str())
+            .and_then(|k| pool_names.get(k))
+        {
+            if !members.iter().any(|m| m == pool_name) {
+                members.push(pool_name.to_string());
+            }
+        } else if let Some(nested) = by_name.get(member.as_str())
+            && visited.insert(nested.name.as_str())
+        {
+            collect_members(nested, by_name, node_keys, pool_names, visited, members);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{GroupKind, ProviderRule, RuleMatch, resolve_groups};
+    use crate::subscription::ClashSubscription;
+
+    const PROFILE: &str = r#"
+proxies:
+  - { name: "SG 01", type: socks5, server: sg.example.com, port: 1080 }
+  - { name: "US 01", type: http, server: us.example.com, port: 8080 }
+proxy-groups:
+  - { name: OpenAI, type: url-test, proxies: [Asia, "US 01", DIRECT], url: "http://www.gstatic.com/generate_204", interval: "300" }
+  - { name: Asia, type: select, proxies: ["SG 01", OpenAI] }
+  - { name: Chain, type: relay, proxies: ["SG 01", "US 01"] }
+rules:
+  - DOMAIN-SUFFIX,openai.com,OpenAI
+  - DOMAIN-KEYWORD,chatgpt,OpenAI
+  - GEOIP,US,OpenAI
+  - DOMAIN,example.com,Asia
+  - MATCH,DIRECT
+"#;
+
+    #[test]
+    fn resolves_nested_groups_and_their_rules() {
+        let parsed: ClashSubscription = serde_yaml::from_str(PROFILE).expect("valid yaml");
+        let pool_names: HashMap<String, &str> = parsed
+            .proxies
+            .iter()
+            .flatten()
+            .map(|n| (n.fingerprint(), n.name.as_str()))
+            .collect();
+
+        let groups = resolve_groups(&parsed, &pool_names, Some("main"));
+
+        assert_eq!(groups.len(), 2, "relay groups are not imported");
+        let openai = &groups[0];
+        assert_eq!(openai.name, "main/OpenAI");
+        assert_eq!(openai.kind, GroupKind::UrlTest);
+        assert_eq!(openai.members, vec!["SG 01", "US 01"]);
+        assert_eq!(openai.interval, Some(300));
+        assert_eq!(
+            openai.rules,
+            vec![
+                ProviderRule {
+                    kind: RuleMatch::DomainSuffix,
+                    value: "openai.com".to_string()
+                },
+                ProviderRule {
+                    kind: RuleMatch::DomainKeyword,
+                    value: "chatgpt".to_string()
+                },
+            ]
+        );
+        assert_eq!(openai.skipped_rules, 1);
+        assert_eq!(groups[1].members, vec!["SG 01", "US 01"]);
+    }
+}
@@ -0,0 +1,788 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+use zip::ZipArchive;
+
+use crate::backend::CoreBackend;
+
+const GITHUB_API: &str = "https://api.github.com/repos";
+/// Directory name used while an install is in progress, before its version is known.
+const STAGING_DIR: &str = ".staging";
+
+pub struct Release {
+    pub version: String,
+    pub asset: ReleaseAsset,
+}
+
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+    /// Hex SHA-256 published by GitHub in the asset `digest` field.
+    pub sha256: Option<String>,
+    /// Checksum file shipped with the release (`<asset>.sha256`,
+    /// `<asset>.dgst`, `sha256sums.txt`), used when there is no digest.
+    pub checksum_url: Option<String>,
+}
+
+/// Names of release files that may carry the checksum of `asset`.
+fn is_checksum_asset(name: &str, asset: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let asset = asset.to_ascii_lowercase();
+    [".sha256", ".sha256sum", ".dgst"]
+        .iter()
+        .any(|ext| name == format!("{asset}{ext}"))
+        || ["sha256sums", "sha256sums.txt", "checksums.txt"].contains(&name.as_str())
+}
+
+fn pick_release_asset(assets: &[serde_json::Value], wanted: &str) -> Option<ReleaseAsset> {
+    let checksum_url = assets
+        .iter()
+        .filter(|a| {
+            a.get("name")
+                .and_then(|v| v.as_str())
+                .is_some_and(|n| is_checksum_asset(n, wanted))
+        })
+        .find_map(|a| a.get("browser_download_url").and_then(|v| v.as_str()))
+        .map(str::to_string);
+    for asset in assets {
+        let name = asset.get("name").and_then(|v| v.as_str())?;
+        let url = asset.get("browser_download_url").and_then(|v| v.as_str())?;
+        if name == wanted && !url.is_empty() {
+            let sha256 = asset
+                .get("digest")
+                .and_then(|v| v.as_str())
+                .and_then(|d| d.strip_prefix("sha256:"))
+                .map(|d| d.to_ascii_lowercase());
+            return Some(ReleaseAsset {
+                name: name.to_string(),
+                url: url.to_string(),
+                sha256,
+                checksum_url,
+            });
+        }
+    }
+    None
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(sha256_hex(&bytes))
+}
+
+pub fn normalize_sha256(raw: &str) -> Result<String> {
+    let lower = raw.trim().to_ascii_lowercase();
+    let hex = lower.strip_prefix("sha256:").unwrap_or(&lower);
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid SHA-256 '{raw}': expected 64 hex characters");
+    }
+    Ok(hex.to_string())
+}
+
+/// Finds the hash of `asset_name` in a checksum file: `sha256sum` lines
+/// (`<hex>  <name>`), a bare hash, or OpenSSL `.dgst` lines (`SHA2-256= <hex>`).
+fn parse_checksum(text: &str, asset_name: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let line = line.trim();
+        let hash = if let Some(hash) = line.strip_prefix("SHA2-256=") {
+            hash.trim()
+        } else {
+            let (hash, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let name = name.trim().trim_start_matches('*');
+            if !name.is_empty() && name.rsplit('/').next() != Some(asset_name) {
+                return None;
+            }
+            hash
+        };
+        normalize_sha256(hash).ok()
+    })
+}
+
+/// The SHA-256 the release publishes for `asset`, from its digest or its
+/// checksum file.
+pub async fn published_sha256(
+    client: &reqwest::Client,
+    asset: &ReleaseAsset,
+) -> Result<Option<String>> {
+    if let Some(sha256) = &asset.sha256 {
+        return Ok(Some(sha256.clone()));
+    }
+    let Some(url) = &asset.checksum_url else {
+        return Ok(None);
+    };
+    let response = client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, "route-cli")
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to download checksums from {url}"))?;
+    let text = response
+        .text()
+        .await
+        .context("Failed to read checksum response body")?;
+    parse_checksum(&text, &asset.name)
+        .with_context(|| format!("No SHA-256 for {} in {url}", asset.name))
+        .map(Some)
+}
+
+/// Checks downloaded bytes against the expected hash. Returns the actual hash.
+/// Without a hash the archive is only installed when `allow_unverified` is set.
+fn verify_sha256(
+    bytes: &[u8],
+    expected: Option<&str>,
+    asset_name: &str,
+    allow_unverified: bool,
+) -> Result<String> {
+    let actual = sha256_hex(bytes);
+    match expected {
+        Some(expected) if expected != actual => bail!(
+            "Checksum mismatch for {asset_name}: expected {expected}, got {actual}. Refusing to install."
+        ),
+        Some(_) => println!("[OK] SHA-256 verified: {actual}"),
+        None if allow_unverified => println!(
+            "[WARN] installing unverified archive {asset_name} (SHA-256 {actual}) because of --no-verify"
+        ),
+        None => bail!(
+            "No published checksum for {asset_name}. Refusing to install; pass --sha256 <HEX> to verify it or --no-verify to skip verification."
+        ),
+    }
+    Ok(actual)
+}
+
+/// Resolves the latest release, or the one tagged `v{version}`, to the
+/// asset for this host.
+pub async fn resolve_release(
+    client: &reqwest::Client,
+    backend: &dyn CoreBackend,
+    version: Option<&str>,
+) -> Result<Release> {
+    let releases = format!("{GITHUB_API}/{}/releases", backend.release_repo());
+    let url = match version {
+        Some(v) => format!("{releases}/tags/v{}", normalize_version(v)),
+        None => format!("{releases}/latest"),
+    };
+    let response = client
+        .get(&url)
+        .header(reqwest::header::USER_AGENT, "route-cli")
+        .send()
+        .await
+        .with_context(|| format!("Failed to query {} release", backend.name()))?;
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND
+        && let Some(v) = version
+    {
+        bail!(
+            "{} release v{} not found",
+            backend.name(),
+            normalize_version(v)
+        );
+    }
+    if !status.is_success() {
+        bail!("Release request failed with status {status}");
+    }
+    let payload_text = response
+        .text()
+        .await
+        .context("Failed to read release response body")?;
+    let payload: serde_json::Value =
+        serde_json::from_str(&payload_text).context("Failed to parse release response JSON")?;
+    let tag = payload
+        .get("tag_name")
+        .and_then(|v| v.as_str())
+        .context("No tag_name found in release response")?;
+    let assets = payload
+        .get("assets")
+        .and_then(|v| v.as_array())
+        .context("No assets found in release response")?;
+    let version = normalize_version(tag);
+    let wanted = backend.host_asset_name(&version)?;
+    let asset = pick_release_asset(assets, &wanted).with_context(|| {
+        format!("No {wanted} release asset found. Use `route-cli install-core --url <archive-url>`")
+    })?;
+    Ok(Release { version, asset })
+}
+
+/// What to keep from a release archive: the core binary, which some
+/// releases name after the platform (`mihomo-windows-amd64.exe`), plus the
+/// DLLs and geo databases shipped next to it.
+fn archive_destination(
+    file_name: &str,
+    backend: &dyn CoreBackend,
+    target_dir: &Path,
+) -> Option<(PathBuf, bool)> {
+    let lower = file_name.to_ascii_lowercase();
+    let binary = backend.binary_name();
+    let platform_named = lower.starts_with(&format!("{}-", backend.name()))
+        && Path::new(&lower).extension() == Path::new(binary).extension();
+    if lower == binary || platform_named {
+        Some((target_dir.join(binary), true))
+    } else if lower.ends_with(".dll") || lower.ends_with(".dat") {
+        Some((target_dir.join(file_name), false))
+    } else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to mark {} as executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn install_core_archive(
+    backend: &dyn CoreBackend,
+    asset_name: &str,
+    bytes: &[u8],
+    target_dir: &Path,
+) -> Result<PathBuf> {
+    let lower = asset_name.to_ascii_lowercase();
+    let installed = if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        install_core_tar_gz(backend, bytes, target_dir)?
+    } else if lower.ends_with(".gz") {
+        install_core_gz(backend, bytes, target_dir)?
+    } else {
+        install_core_zip(backend, bytes, target_dir)?
+    };
+    make_executable(&installed)?;
+    Ok(installed)
+}
+
+/// Releases that ship the binary itself, gzip-compressed.
+fn install_core_gz(backend: &dyn CoreBackend, bytes: &[u8], target_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(target_dir)
+        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+    let mut buf = Vec::new();
+    flate2::read::GzDecoder::new(Cursor::new(bytes))
+        .read_to_end(&mut buf)
+        .with_context(|| format!("Invalid {} gzip archive", backend.name()))?;
+    let destination = target_dir.join(backend.binary_name());
+    fs::write(&destination, buf)
+        .with_context(|| format!("Failed to write {}", destination.display()))?;
+    Ok(destination)
+}
+
+fn install_core_tar_gz(
+    backend: &dyn CoreBackend,
+    bytes: &[u8],
+    target_dir: &Path,
+) -> Result<PathBuf> {
+    fs::create_dir_all(target_dir)
+        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(Cursor::new(bytes)));
+
+    let mut installed_exe: Option<PathBuf> = None;
+    for entry in archive
+        .entries()
+        .with_context(|| format!("Invalid {} tar.gz archive", backend.name()))?
+    {
+        let mut entry = entry.context("Failed reading tar entry")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path().context("Invalid tar entry path")?.into_owned();
+        let Some(file_name) = name.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let Some((destination, is_binary)) = archive_destination(file_name, backend, target_dir)
+        else {
+            continue;
+        };
+
+        let mut buf = Vec::new();
+        entry
+            .read_to_end(&mut buf)
+            .with_context(|| format!("Failed to extract tar entry: {}", name.display()))?;
+        fs::write(&destination, buf)
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+        if is_binary {
+            installed_exe = Some(destination);
+        }
+    }
+
+    installed_exe.with_context(|| {
+        format!(
+            "{} not found in downloaded tar.gz archive",
+            backend.binary_name()
+        )
+    })
+}
+
+fn install_core_zip(backend: &dyn CoreBackend, bytes: &[u8], target_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(target_dir)
+        .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+    let mut archive = ZipArchive::new(Cursor::new(bytes))
+        .with_context(|| format!("Invalid {} zip archive", backend.name()))?;
+
+    let mut installed_exe: Option<PathBuf> = None;
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .with_context(|| format!("Failed reading zip entry #{i}"))?;
+        if !entry.is_file() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let Some(file_name) = name.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        let Some((destination, is_binary)) = archive_destination(file_name, backend, target_dir)
+        else {
+            continue;
+        };
+
+        let mut buf = Vec::new();
+        entry
+            .read_to_end(&mut buf)
+            .with_context(|| format!("Failed to extract zip entry: {}", name.display()))?;
+        fs::write(&destination, buf)
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+        if is_binary {
+            installed_exe = Some(destination);
+        }
+    }
+
+    installed_exe.with_context(|| {
+        format!(
+            "{} not found in downloaded zip archive",
+            backend.binary_name()
+        )
+    })
+}
+
+fn local_bundle_candidates(backend: &dyn CoreBackend) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    let mut push_unique = |p: PathBuf| {
+        if !candidates.iter().any(|x| x == &p) {
+            candidates.push(p);
+        }
+    };
+
+    push_unique(PathBuf::from("tools").join(backend.name()));
+    if let Ok(cwd) = std::env::current_dir() {
+        push_unique(cwd.join("tools").join(backend.name()));
+    }
+    if let Ok(exe) = std::env::current_exe()
+        && let Some(exe_dir) = exe.parent()
+    {
+        push_unique(exe_dir.join("tools").join(backend.name()));
+    }
+    candidates
+}
+
+pub fn install_core_from_local_bundle(
+    backend: &dyn CoreBackend,
+    target_dir: &Path,
+) -> Result<Option<(PathBuf, PathBuf)>> {
+    for source_dir in local_bundle_candidates(backend) {
+        let source_exe = source_dir.join(backend.binary_name());
+        if !source_exe.exists() {
+            continue;
+        }
+
+        fs::create_dir_all(target_dir)
+            .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+
+        let target_exe = target_dir.join(backend.binary_name());
+        fs::copy(&source_exe, &target_exe).with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                source_exe.display(),
+                target_exe.display()
+            )
+        })?;
+        make_executable(&target_exe)?;
+
+        for entry in fs::read_dir(&source_dir)
+            .with_context(|| format!("Failed to read {}", source_dir.display()))?
+        {
+            let entry = entry.with_context(|| {
+                format!("Failed to read directory entry in {}", source_dir.display())
+            })?;
+            let file_type = entry
+                .file_type()
+                .with_context(|| format!("Failed to get type for {}", entry.path().display()))?;
+            if !file_type.is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let ext = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            if !ext.eq_ignore_ascii_case("dll") {
+                continue;
+            }
+            let file_name = entry.file_name();
+            let target_path = target_dir.join(&file_name);
+            fs::copy(&path, &target_path).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    path.display(),
+                    target_path.display()
+                )
+            })?;
+        }
+
+        return Ok(Some((source_dir, target_exe)));
+    }
+
+    Ok(None)
+}
+
+pub async fn download_and_install_core(
+    client: &reqwest::Client,
+    backend: &dyn CoreBackend,
+    asset_name: &str,
+    download_url: &str,
+    expected_sha256: Option<&str>,
+    allow_unverified: bool,
+    target_dir: &Path,
+) -> Result<PathBuf> {
+    println!("Installing {} asset: {asset_name}", backend.name());
+    println!("Download URL: {download_url}");
+
+    let response = client
+        .get(download_url)
+        .header(reqwest::header::USER_AGENT, "route-cli")
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {} from {download_url}", backend.name()))?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!("Download request failed with status {status}");
+    }
+    let archive_bytes = response
+        .bytes()
+        .await
+        .context("Failed to read archive response bytes")?;
+    verify_sha256(
+        archive_bytes.as_ref(),
+        expected_sha256,
+        asset_name,
+        allow_unverified,
+    )?;
+    install_core_archive(backend, asset_name, archive_bytes.as_ref(), target_dir)
+}
+
+/// Archive file name for a user-supplied `--url`, used to pick the extractor.
+pub fn asset_name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    match path.rsplit('/').next() {
+        Some(name) if name.contains('.') => name.to_string(),
+        _ if cfg!(windows) => "custom.zip".to_string(),
+        _ => "custom.tar.gz".to_string(),
+    }
+}
+
+/// Strips a leading `v` from a release tag.
+pub fn normalize_version(raw: &str) -> String {
+    raw.trim().trim_start_matches('v').to_string()
+}
+
+pub async fn detect_core_version(backend: &dyn CoreBackend, core_path: &str) -> Option<String> {
+    let output = Command::new(core_path)
+        .args(backend.version_args())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    backend.parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Orders versions like `1.11.4` < `1.12.0-beta.1` < `1.12.0`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn split(v: &str) -> (Vec<u64>, Option<&str>) {
+        let (core, pre) = match v.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (v, None),
+        };
+        let numbers = core.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+        (numbers, pre)
+    }
+    let (a_core, a_pre) = split(a);
+    let (b_core, b_pre) = split(b);
+    a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(b),
+    })
+}
+
+/// Directory the next install is extracted into before it is versioned.
+pub fn staging_dir(bin_dir: &Path) -> PathBuf {
+    bin_dir.join(STAGING_DIR)
+}
+
+/// Returns an empty staging directory, discarding leftovers of an
+/// interrupted install.
+pub fn prepare_staging(bin_dir: &Path) -> Result<PathBuf> {
+    let staging = staging_dir(bin_dir);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("Failed to remove {}", staging.display()))?;
+    }
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+    Ok(staging)
+}
+
+pub fn version_dir(bin_dir: &Path, version: &str) -> PathBuf {
+    bin_dir.join(version)
+}
+
+/// Moves a staged install to `<bin_dir>/<version>/`, replacing an earlier
+/// install of the same version. Returns the path of the installed binary.
+pub fn finalize_install(bin_dir: &Path, version: &str, binary_name: &str) -> Result<PathBuf> {
+    let staging = staging_dir(bin_dir);
+    let target = version_dir(bin_dir, version);
+    if target.exists() {
+        fs::remove_dir_all(&target)
+            .with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+    fs::rename(&staging, &target).with_context(|| {
+        format!(
+            "Failed to move {} to {}",
+            staging.display(),
+            target.display()
+        )
+    })?;
+    Ok(target.join(binary_name))
+}
+
+/// Versions with a binary under `<bin_dir>/<version>/`, oldest first.
+pub fn installed_versions(bin_dir: &Path, binary_name: &str) -> Result<Vec<String>> {
+    if !bin_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut versions = Vec::new();
+    for entry in
+        fs::read_dir(bin_dir).with_context(|| format!("Failed to read {}", bin_dir.display()))?
+    {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory entry in {}", bin_dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == STAGING_DIR || !entry.path().join(binary_name).is_file() {
+            continue;
+        }
+        versions.push(name);
+    }
+    versions.sort_by(|a, b| compare_versions(a, b));
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use serde_json::json;
+
+    use super::{
+        asset_name_from_url, compare_versions, finalize_install, install_core_archive,
+        installed_versions, normalize_sha256, parse_checksum, pick_release_asset, prepare_staging,
+        verify_sha256,
+    };
+    use crate::backend::CoreBackend;
+    use crate::mihomo::Mihomo;
+    use crate::singbox::SingBox;
+    use crate::test_util::make_temp_dir;
+
+    #[test]
+    fn picks_release_asset_for_platform() {
+        let assets = vec![
+            json!({
+                "name": "sing-box-1.12.20-linux-amd64.tar.gz",
+                "browser_download_url": "https://example.com/linux.tar.gz"
+            }),
+            json!({
+                "name": "sing-box-1.12.20-windows-amd64.zip",
+                "browser_download_url": "https://example.com/windows.zip",
+                "digest": "sha256:ABC123"
+            }),
+        ];
+        let selected = pick_release_asset(&assets, "sing-box-1.12.20-windows-amd64.zip")
+            .expect("should pick windows asset");
+        assert_eq!(selected.name, "sing-box-1.12.20-windows-amd64.zip");
+        assert_eq!(selected.url, "https://example.com/windows.zip");
+        assert_eq!(selected.sha256.as_deref(), Some("abc123"));
+
+        let selected = pick_release_asset(&assets, "sing-box-1.12.20-linux-amd64.tar.gz")
+            .expect("should pick linux asset");
+        assert_eq!(selected.url, "https://example.com/linux.tar.gz");
+        assert_eq!(selected.sha256, None);
+        assert_eq!(selected.checksum_url, None);
+        assert!(pick_release_asset(&assets, "sing-box-1.12.20-darwin-arm64.tar.gz").is_none());
+    }
+
+    #[test]
+    fn derives_asset_name_from_url() {
+        assert_eq!(
+            asset_name_from_url("https://example.com/dl/sing-box-linux-arm64.tar.gz?x=1"),
+            "sing-box-linux-arm64.tar.gz"
+        );
+        assert!(asset_name_from_url("https://example.com/download").starts_with("custom."));
+    }
+
+    #[test]
+    fn installs_binary_from_tar_gz() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (path, body) in [
+            (format!("sing-box-1.12.20/{}", SingBox.binary_name()), "bin"),
+            ("sing-box-1.12.20/LICENSE".to_string(), "license"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, body.as_bytes())
+                .expect("tar entry should be appended");
+        }
+        let bytes = builder
+            .into_inner()
+            .and_then(|gz| gz.finish())
+            .expect("archive should be finished");
+        let target = make_temp_dir("route-cli-tar-install");
+
+        let installed = install_core_archive(&SingBox, "sing-box.tar.gz", &bytes, &target)
+            .expect("tar.gz should install");
+
+        assert_eq!(installed, target.join(SingBox.binary_name()));
+        assert_eq!(
+            std::fs::read_to_string(&installed).expect("binary should be readable"),
+            "bin"
+        );
+        assert!(!target.join("LICENSE").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&installed)
+                .expect("metadata should be readable")
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+    }
+
+    #[test]
+    fn refuses_archive_with_checksum_mismatch() {
+        let expected = normalize_sha256(
+            "SHA256:2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824",
+        )
+        .expect("checksum should normalize");
+
+        assert_eq!(
+            verify_sha256(b"hello", Some(&expected), "a.zip", false).expect("hash should match"),
+            expected
+        );
+        assert!(verify_sha256(b"tampered", Some(&expected), "a.zip", false).is_err());
+        assert!(normalize_sha256("abc").is_err());
+    }
+
+    #[test]
+    fn falls_back_to_checksum_files_without_a_digest() {
+        let assets = vec![
+            json!({
+                "name": "Xray-linux-64.zip",
+                "browser_download_url": "https://example.com/Xray-linux-64.zip"
+            }),
+            json!({
+                "name": "Xray-linux-64.zip.dgst",
+                "browser_download_url": "https://example.com/Xray-linux-64.zip.dgst"
+            }),
+        ];
+        let selected = pick_release_asset(&assets, "Xray-linux-64.zip").unwrap();
+        assert_eq!(selected.sha256, None);
+        assert_eq!(
+            selected.checksum_url.as_deref(),
+            Some("https://example.com/Xray-linux-64.zip.dgst")
+        );
+
+        let hash = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let dgst = format!("MD5= 5d41402abc4b2a76b9719d911017c592\nSHA2-256= {hash}\n");
+        assert_eq!(parse_checksum(&dgst, "a.zip").as_deref(), Some(hash));
+        let sums = format!("{}  b.zip\n{hash} *a.zip\n", "0".repeat(64));
+        assert_eq!(parse_checksum(&sums, "a.zip").as_deref(), Some(hash));
+        assert_eq!(parse_checksum(&sums, "c.zip"), None);
+        assert_eq!(parse_checksum(hash, "a.zip").as_deref(), Some(hash));
+
+        // No digest and no checksum file: refuse unless verification is skipped.
+        let err = verify_sha256(b"hello", None, "a.zip", false).unwrap_err();
+        assert!(err.to_string().contains("--no-verify"));
+        assert_eq!(verify_sha256(b"hello", None, "a.zip", true).unwrap(), hash);
+    }
+
+    #[test]
+    fn installs_gzipped_binary() {
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gz, b"mihomo").expect("binary should compress");
+        let bytes = gz.finish().expect("gzip should finish");
+        let target = make_temp_dir("route-cli-gz-install");
+
+        let installed =
+            install_core_archive(&Mihomo, "mihomo-linux-amd64-v1.19.1.gz", &bytes, &target)
+                .expect("gz should install");
+
+        assert_eq!(installed, target.join(Mihomo.binary_name()));
+        assert_eq!(
+            std::fs::read_to_string(&installed).expect("binary should be readable"),
+            "mihomo"
+        );
+    }
+
+    #[test]
+    fn orders_core_versions() {
+        assert_eq!(compare_versions("1.9.0", "1.12.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.12.0-beta.1", "1.12.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.12.0", "1.12.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn finalizes_staged_install_into_version_dir() {
+        let bin_dir = make_temp_dir("route-cli-versions");
+        for version in ["1.12.0", "1.9.3"] {
+            let staging = prepare_staging(&bin_dir).expect("staging should be created");
+            std::fs::write(staging.join(SingBox.binary_name()), version)
+                .expect("staged binary should be written");
+            let installed = finalize_install(&bin_dir, version, SingBox.binary_name())
+                .expect("install should finalize");
+            assert_eq!(installed, bin_dir.join(version).join(SingBox.binary_name()));
+        }
+
+        assert!(!bin_dir.join(".staging").exists());
+        assert_eq!(
+            installed_versions(&bin_dir, SingBox.binary_name()).expect("versions should be listed"),
+            vec!["1.9.3", "1.12.0"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AppPaths, write_private};
+use crate::subscription::ProxyNode;
+
+/// Outcome of the last ping of one node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LatencyRecord {
+    /// Round trip in milliseconds; `None` when the node did not answer.
+    pub ms: Option<u64>,
+    /// Unix timestamp in seconds.
+    pub at: u64,
+}
+
+/// Last ping results by node fingerprint, so history survives provider
+/// renames and same-named nodes of different subscriptions stay apart. A
+/// missing or unreadable file is treated as no history, since it only
+/// decorates listings.
+pub fn load_latencies(paths: &AppPaths) -> BTreeMap<String, LatencyRecord> {
+    fs::read_to_string(&paths.latency_json)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Last ping result of `node`.
+pub fn node_latency<'a>(
+    latencies: &'a BTreeMap<String, LatencyRecord>,
+    node: &ProxyNode,
+) -> Option<&'a LatencyRecord> {
+    latencies.get(&node.fingerprint())
+}
+
+/// Merges new ping results, keyed by node fingerprint, into the history.
+pub fn record_latencies(paths: &AppPaths, results: &[(String, LatencyRecord)]) -> Result<()> {
+    if results.is_empty() {
+        return Ok(());
+    }
+    let mut latencies = load_latencies(paths);
+    for (fingerprint, record) in results {
+        latencies.insert(fingerprint.clone(), *record);
+    }
+    paths.ensure_dirs()?;
+    let json = serde_json::to_string_pretty(&latencies).context("Failed to serialize latencies")?;
+    write_private(&paths.latency_json, json)
+}
+
+/// Reads the round trip from `ping` output: `time=12.3 ms` on Unix,
+/// `time=12ms`, `time<1ms` or `时间=12ms` on Windows.
+pub fn parse_ping_time(output: &str) -> Option<u64> {
+    ["time=", "time<", "时间=", "时间<"]
+        .iter()
+        .find_map(|marker| {
+            let rest = &output[output.find(marker)? + marker.len()..];
+            let number: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect();
+            number.parse::<f64>().ok().map(|ms| ms.round() as u64)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{LatencyRecord, node_latency, parse_ping_time};
+    use crate::subscription::ProxyNode;
+
+    #[test]
+    fn parses_ping_round_trip() {
+        assert_eq!(
+            parse_ping_time("64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=12.6 ms"),
+            Some(13)
+        );
+        assert_eq!(
+            parse_ping_time("Reply from 1.1.1.1: bytes=32 time<1ms TTL=57"),
+            Some(1)
+        );
+        assert_eq!(
+            parse_ping_time("来自 1.1.1.1 的回复: 字节=32 时间=35ms TTL=57"),
+            Some(35)
+        );
+        assert_eq!(parse_ping_time("Request timed out."), None);
+    }
+
+    #[test]
+    fn keys_history_by_node_not_name() {
+        let nodes: Vec<ProxyNode> = serde_yaml::from_str(
+            "[{ name: SG 01, type: ss, server: a.example, port: 8388, password: a },
+              { name: SG 01, type: ss, server: b.example, port: 8388, password: b }]",
+        )
+        .unwrap();
+        let mut latencies = BTreeMap::new();
+        latencies.insert(
+            nodes[0].fingerprint(),
+            LatencyRecord {
+                ms: Some(42),
+                at: 1,
+            },
+        );
+        assert_eq!(node_latency(&latencies, &nodes[0]).unwrap().ms, Some(42));
+        assert!(node_latency(&latencies, &nodes[1]).is_none());
+
+        let mut renamed = nodes[0].clone();
+        renamed.name = "SG-01 x1.0".to_string();
+        assert!(node_latency(&latencies, &renamed).is_some());
+    }
+}
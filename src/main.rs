@@ -1,7 +1,24 @@
+mod backend;
+mod chain;
 mod commands;
 mod config;
+mod diff;
+mod groups;
+mod install;
+mod latency;
+mod mihomo;
+mod net;
+mod output;
+mod picker;
 mod proxy;
+mod region;
+mod secrets;
+mod selector;
+mod singbox;
 mod subscription;
+#[cfg(test)]
+mod test_util;
+mod xray;
 
 use clap::{Parser, Subcommand};
 
@@ -12,6 +29,9 @@ use clap::{Parser, Subcommand};
     about = "Run any CLI command with process-scoped proxy routing"
 )]
 struct Cli {
+    /// Output format of list-nodes, doctor, sub-info and update
+    #[arg(long, global = true, value_enum, default_value_t)]
+    output: output::OutputFormat,
     #[command(subcommand)]
     command: Commands,
 }
@@ -21,17 +41,104 @@ enum Commands {
     InstallCore {
         #[arg(long)]
         url: Option<String>,
+        /// Expected SHA-256 of the downloaded archive
+        #[arg(long)]
+        sha256: Option<String>,
+        /// Install this release (e.g. 1.11.4) instead of the latest
+        #[arg(long, conflicts_with = "url")]
+        version: Option<String>,
+        /// Core to install and switch to (default: `proxy_core.kind`)
+        #[arg(long, value_enum)]
+        kind: Option<config::CoreKind>,
+        /// Install even when no checksum is published or given
+        #[arg(long, conflicts_with = "sha256")]
+        no_verify: bool,
+    },
+    UpgradeCore {
+        /// Install even when the release publishes no checksum
+        #[arg(long)]
+        no_verify: bool,
+    },
+    RollbackCore {
+        /// Installed version to switch to (default: the previously active one)
+        #[arg(long)]
+        to: Option<String>,
     },
     LoginSub {
+        /// http(s):// or file:// subscription URL
+        #[arg(long, required_unless_present = "file", conflicts_with = "file")]
+        url: Option<String>,
+        /// Import a subscription file, or `-` for stdin
+        #[arg(long)]
+        file: Option<String>,
+        #[arg(long, default_value = config::DEFAULT_SUBSCRIPTION_NAME)]
+        name: String,
+        #[arg(long)]
+        user_agent: Option<String>,
+        /// Extra request header as 'Name: value' (repeatable)
+        #[arg(long = "header")]
+        headers: Vec<String>,
+        #[arg(long, value_enum)]
+        format: Option<config::SubscriptionFormat>,
+        /// Keep the http(s) URL in the OS keyring or the encrypted secrets.json
+        #[arg(long, value_enum)]
+        store: Option<config::SecretStore>,
+    },
+    RemoveSub {
+        name: String,
+    },
+    ProtectSub {
+        /// Subscription to protect (default: all with an http(s) URL)
+        name: Option<String>,
+        #[arg(long, value_enum)]
+        store: config::SecretStore,
+    },
+    Update {
+        name: Option<String>,
+        /// Download even if the server reports the cache is current
+        #[arg(long)]
+        force: bool,
+        /// Show what would change without replacing the cache
         #[arg(long)]
-        url: String,
+        dry_run: bool,
+        /// Read the subscription from a file, or `-` for stdin
+        #[arg(long, conflicts_with = "force")]
+        from: Option<String>,
+    },
+    SubInfo {
+        name: Option<String>,
+    },
+    AddNode {
+        /// e.g. '{ name: office, type: socks5, server: 10.0.0.1, port: 1080 }'
+        node: String,
+    },
+    RemoveNode {
+        name: String,
     },
-    Update,
     ListNodes,
+    /// Select a node; opens the interactive picker without a name
     UseNode {
-        node_name: String,
+        /// Node name, `#<index>` from list-nodes, or `region:<code>`
+        node_name: Option<String>,
+    },
+    /// Pick a node interactively (same as `use-node` without a name)
+    Pick,
+    ListGroups,
+    UseGroup {
+        group_name: String,
+    },
+    RenderConfig {
+        /// Node selector as for use-node (default: the selected node)
+        #[arg(long)]
+        node: Option<String>,
+        /// Print the generated JSON instead of its path
+        #[arg(long)]
+        print: bool,
     },
     Run {
+        /// Use this node for one run without saving it (same selectors as use-node)
+        #[arg(long)]
+        node: Option<String>,
         #[arg(trailing_var_arg = true, required = true)]
         command: Vec<String>,
     },
@@ -47,13 +154,55 @@ async fn main() {
 
     let cli = Cli::parse();
     let result = match cli.command {
-        Commands::InstallCore { url } => commands::cmd_install_core(url).await.map(|_| 0),
-        Commands::LoginSub { url } => commands::cmd_login_sub(url).await.map(|_| 0),
-        Commands::Update => commands::cmd_update().await.map(|_| 0),
-        Commands::ListNodes => commands::cmd_list_nodes().await.map(|_| 0),
+        Commands::InstallCore {
+            url,
+            sha256,
+            version,
+            kind,
+            no_verify,
+        } => commands::cmd_install_core(url, sha256, version, kind, no_verify)
+            .await
+            .map(|_| 0),
+        Commands::UpgradeCore { no_verify } => {
+            commands::cmd_upgrade_core(no_verify).await.map(|_| 0)
+        }
+        Commands::RollbackCore { to } => commands::cmd_rollback_core(to).await.map(|_| 0),
+        Commands::LoginSub {
+            url,
+            file,
+            name,
+            user_agent,
+            headers,
+            format,
+            store,
+        } => commands::cmd_login_sub(name, url, file, user_agent, headers, format, store)
+            .await
+            .map(|_| 0),
+        Commands::RemoveSub { name } => commands::cmd_remove_sub(name).await.map(|_| 0),
+        Commands::ProtectSub { name, store } => {
+            commands::cmd_protect_sub(name, store).await.map(|_| 0)
+        }
+        Commands::Update {
+            name,
+            force,
+            dry_run,
+            from,
+        } => commands::cmd_update(name, force, dry_run, from, cli.output)
+            .await
+            .map(|_| 0),
+        Commands::SubInfo { name } => commands::cmd_sub_info(name, cli.output).await.map(|_| 0),
+        Commands::AddNode { node } => commands::cmd_add_node(node).await.map(|_| 0),
+        Commands::RemoveNode { name } => commands::cmd_remove_node(name).await.map(|_| 0),
+        Commands::ListNodes => commands::cmd_list_nodes(cli.output).await.map(|_| 0),
         Commands::UseNode { node_name } => commands::cmd_use_node(node_name).await.map(|_| 0),
-        Commands::Run { command } => commands::cmd_run(command).await,
-        Commands::Doctor => commands::cmd_doctor().await.map(|_| 0),
+        Commands::Pick => commands::cmd_use_node(None).await.map(|_| 0),
+        Commands::ListGroups => commands::cmd_list_groups().await.map(|_| 0),
+        Commands::UseGroup { group_name } => commands::cmd_use_group(group_name).await.map(|_| 0),
+        Commands::RenderConfig { node, print } => {
+            commands::cmd_render_config(node, print).await.map(|_| 0)
+        }
+        Commands::Run { node, command } => commands::cmd_run(command, node).await,
+        Commands::Doctor => commands::cmd_doctor(cli.output).await.map(|_| 0),
     };
 
     match result {
@@ -0,0 +1,231 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+
+use crate::backend::{
+    CoreBackend, CoreVersion, Egress, Unsupported, host_labels, reject_sing_box_routing, required,
+};
+use crate::chain::Chain;
+use crate::config::AppConfig;
+use crate::subscription::ProxyNode;
+
+const BINARY: &str = if cfg!(windows) {
+    "mihomo.exe"
+} else {
+    "mihomo"
+};
+
+/// mihomo (Clash.Meta) reads Clash proxies natively, so nodes are passed
+/// through as they appear in the subscription.
+pub struct Mihomo;
+
+impl CoreBackend for Mihomo {
+    fn name(&self) -> &'static str {
+        "mihomo"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        BINARY
+    }
+
+    fn config_extension(&self) -> &'static str {
+        "yaml"
+    }
+
+    fn minimum_version(&self) -> CoreVersion {
+        CoreVersion::new(1, 18, 0)
+    }
+
+    fn check(&self, node: &ProxyNode) -> Result<(), Unsupported> {
+        required(node.server.as_ref(), "server")?;
+        required(node.port, "port")?;
+        Ok(())
+    }
+
+    fn render_config(
+        &self,
+        cfg: &AppConfig,
+        egress: &Egress,
+        chain: &Chain,
+        listen_port: u16,
+        route_all: bool,
+        _version: CoreVersion,
+    ) -> Result<String> {
+        reject_sing_box_routing(cfg, self.name())?;
+        let mut config = Mapping::new();
+        config.insert("mixed-port".into(), listen_port.into());
+        config.insert("bind-address".into(), "127.0.0.1".into());
+        config.insert("allow-lan".into(), false.into());
+        config.insert("mode".into(), "rule".into());
+        config.insert("log-level".into(), "warning".into());
+
+        // Each chain link is dialed through the previous one via `dialer-proxy`.
+        let mut chain_proxies = Vec::new();
+        for (i, node) in chain.links().into_iter().enumerate() {
+            let mut proxy = clash_proxy(node, &format!("chain-{}", i + 1))?;
+            if i > 0 {
+                proxy["dialer-proxy"] = Value::from(format!("chain-{i}"));
+            }
+            chain_proxies.push(proxy);
+        }
+
+        let mut proxies = match egress {
+            Egress::Node(node) => vec![clash_proxy(node, "proxy")?],
+            Egress::Group(_, members) => members
+                .iter()
+                .enumerate()
+                .map(|(i, node)| clash_proxy(node, &format!("proxy-{}", i + 1)))
+                .collect::<Result<Vec<Value>>>()?,
+        };
+        if let Some(last) = chain_proxies.last() {
+            for proxy in &mut proxies {
+                proxy["dialer-proxy"] = last["name"].clone();
+            }
+        }
+        if let Egress::Group(group, _) = egress {
+            let mut proxy_group = Mapping::new();
+            proxy_group.insert("name".into(), "proxy".into());
+            proxy_group.insert("type".into(), group.kind.clash_name().into());
+            proxy_group.insert(
+                "proxies".into(),
+                proxies
+                    .iter()
+                    .map(|p| p["name"].clone())
+                    .collect::<Vec<_>>()
+                    .into(),
+            );
+            if let Some(url) = &group.url {
+                proxy_group.insert("url".into(), url.as_str().into());
+            }
+            if let Some(interval) = group.interval {
+                proxy_group.insert("interval".into(), interval.into());
+            }
+            if let Some(tolerance) = group.tolerance {
+                proxy_group.insert("tolerance".into(), tolerance.into());
+            }
+            config.insert("proxy-groups".into(), vec![proxy_group].into());
+        }
+        proxies.extend(chain_proxies);
+        config.insert("proxies".into(), proxies.into());
+
+        let mut rules: Vec<String> = egress
+            .provider_rules(cfg)
+            .iter()
+            .map(|rule| format!("{},{},proxy", rule.kind.clash_name(), rule.value))
+            .collect();
+        if route_all {
+            rules.push("MATCH,proxy".to_string());
+        } else {
+            rules.extend(
+                cfg.routing
+                    .proxy_domains
+                    .iter()
+                    .map(|domain| format!("DOMAIN-SUFFIX,{domain},proxy")),
+            );
+            rules.push("MATCH,DIRECT".to_string());
+        }
+        config.insert("rules".into(), rules.into());
+        serde_yaml::to_string(&config).context("Failed to serialize mihomo config")
+    }
+
+    fn run_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec![
+            "-d".into(),
+            home_dir(config_path).into(),
+            "-f".into(),
+            config_path.into(),
+        ]
+    }
+
+    fn check_args(&self, config_path: &Path) -> Vec<OsString> {
+        let mut args = vec!["-t".into()];
+        args.extend(self.run_args(config_path));
+        args
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        &["-v"]
+    }
+
+    fn release_repo(&self) -> &'static str {
+        "MetaCubeX/mihomo"
+    }
+
+    fn host_asset_name(&self, version: &str) -> Result<String> {
+        let (os, arch) = host_labels(
+            self.name(),
+            ["windows", "linux", "darwin"],
+            ["amd64", "arm64", "386"],
+        )?;
+        let ext = if os == "windows" { "zip" } else { "gz" };
+        Ok(format!("mihomo-{os}-{arch}-v{version}.{ext}"))
+    }
+}
+
+/// The node as a Clash proxy renamed to `name`, so names from the
+/// subscription cannot clash with the generated group or rules.
+fn clash_proxy(node: &ProxyNode, name: &str) -> Result<Value> {
+    let mut proxy = serde_yaml::to_value(node).context("Failed to serialize node")?;
+    strip_nulls(&mut proxy);
+    proxy["name"] = Value::from(name);
+    Ok(proxy)
+}
+
+/// mihomo keeps its cache and geo databases next to the generated config.
+fn home_dir(config_path: &Path) -> &Path {
+    config_path.parent().unwrap_or(Path::new("."))
+}
+
+/// Drops unset optional fields so mihomo only sees keys the node defines.
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            map.retain(|_, v| !v.is_null());
+            for v in map.values_mut() {
+                strip_nulls(v);
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mihomo;
+    use crate::backend::{CoreBackend, CoreVersion, Egress};
+    use crate::chain::Chain;
+    use crate::config::AppConfig;
+    use crate::subscription::ProxyNode;
+
+    #[test]
+    fn passes_clash_node_through() {
+        let node: ProxyNode = serde_yaml::from_str(
+            "{ name: 'HK, 01', type: hysteria2, server: h.example, port: 443, password: pw, up: 50 }",
+        )
+        .unwrap();
+        let cfg = AppConfig::default();
+
+        let rendered = Mihomo
+            .render_config(
+                &cfg,
+                &Egress::Node(&node),
+                &Chain::default(),
+                27890,
+                false,
+                CoreVersion::new(1, 19, 0),
+            )
+            .unwrap();
+        let config: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+
+        let proxy = &config["proxies"][0];
+        assert_eq!(proxy["name"], "proxy");
+        assert_eq!(proxy["type"], "hysteria2");
+        assert_eq!(proxy["up"], 50);
+        assert!(proxy.get("uuid").is_none());
+        assert_eq!(config["rules"][0], "DOMAIN-SUFFIX,openai.com,proxy");
+        assert_eq!(config["mixed-port"], 27890);
+    }
+}
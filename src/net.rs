@@ -0,0 +1,122 @@
+use std::fs;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::process::Child;
+
+use crate::backend::backend_for;
+use crate::chain::Chain;
+use crate::config::{AppConfig, AppPaths, NetworkConfig, resolve_proxy_core_path};
+use crate::proxy::{
+    check_core_config, generate_bootstrap_config, resolve_core_version, spawn_proxy_core,
+    stop_process, wait_port_open,
+};
+use crate::secrets::redact_url;
+use crate::selector::resolve_selected;
+use crate::subscription::load_node_pool;
+
+/// HTTP client for route-cli's own downloads, optionally backed by a
+/// temporary proxy core that must be stopped with [`DownloadClient::finish`].
+pub struct DownloadClient {
+    pub client: reqwest::Client,
+    /// Problems worth reporting to the user, e.g. a bootstrap fallback.
+    /// Left to the caller so background downloads stay quiet.
+    pub warnings: Vec<String>,
+    bootstrap: Option<Child>,
+}
+
+impl DownloadClient {
+    /// Builds a client from `[network]`. With `bootstrap_via_node`, starts a
+    /// proxy core on the last-known-good node and routes through it; when
+    /// that is not possible, falls back to the configured upstream proxy.
+    pub async fn connect(cfg: &AppConfig, paths: &AppPaths) -> Result<Self> {
+        let mut warnings = Vec::new();
+        if cfg.network.bootstrap_via_node {
+            match start_bootstrap(cfg, paths).await {
+                Ok(core) => {
+                    let proxy = format!("http://127.0.0.1:{}", cfg.network.bootstrap_port);
+                    let client = build_client(&cfg.network, Some(&proxy))?;
+                    return Ok(Self {
+                        client,
+                        warnings,
+                        bootstrap: Some(core),
+                    });
+                }
+                Err(err) => warnings.push(format!("bootstrap via node unavailable: {err:#}")),
+            }
+        }
+        let client = build_client(&cfg.network, cfg.network.proxy.as_deref())?;
+        Ok(Self {
+            client,
+            warnings,
+            bootstrap: None,
+        })
+    }
+
+    pub async fn finish(mut self) -> Result<()> {
+        if let Some(core) = self.bootstrap.as_mut() {
+            stop_process(core).await?;
+        }
+        Ok(())
+    }
+}
+
+fn build_client(network: &NetworkConfig, proxy: Option<&str>) -> Result<reqwest::Client> {
+    let timeout = Duration::from_secs(network.timeout_secs);
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(timeout)
+        .read_timeout(timeout);
+    if let Some(proxy) = proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .with_context(|| format!("Invalid network.proxy URL '{}'", redact_url(proxy)))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = network.ca_bundle.as_deref() {
+        let pem = fs::read(path).with_context(|| format!("Failed to read CA bundle {path}"))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid PEM certificates in {path}"))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    builder.build().context("Failed to build HTTP client")
+}
+
+async fn start_bootstrap(cfg: &AppConfig, paths: &AppPaths) -> Result<Child> {
+    let selected = cfg
+        .runtime
+        .selected_node
+        .as_deref()
+        .context("No last-known-good node; run `route-cli run` or `use-node` once first")?;
+    let nodes = load_node_pool(cfg, paths)?;
+    let node = resolve_selected(&cfg.runtime, &nodes)
+        .with_context(|| format!("Node '{selected}' is no longer in the cached subscriptions"))?;
+
+    let core_version = resolve_core_version(cfg).await?;
+    let chain = Chain::resolve(cfg, &nodes)?;
+    generate_bootstrap_config(cfg, node, &chain, paths, core_version)?;
+    let backend = backend_for(cfg.proxy_core.kind);
+    let config_path = paths.bootstrap_config(backend);
+    let core_path = resolve_proxy_core_path(&cfg.proxy_core);
+    check_core_config(backend, &core_path, &config_path).await?;
+    let mut core = spawn_proxy_core(backend, &core_path, &config_path).await?;
+    if let Err(err) = wait_port_open(cfg.network.bootstrap_port, Duration::from_secs(8)).await {
+        stop_process(&mut core).await?;
+        return Err(err);
+    }
+    Ok(core)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::build_client;
+    use crate::config::NetworkConfig;
+
+    #[test]
+    fn rejects_invalid_proxy_url() {
+        let network = NetworkConfig::default();
+
+        assert!(build_client(&network, Some("http://proxy.example:8080")).is_ok());
+        assert!(build_client(&network, Some("not a url")).is_err());
+    }
+}
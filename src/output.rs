@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// `--output` format of listing and diagnostic commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns and `[OK]` lines for people.
+    #[default]
+    Table,
+    /// One stable JSON document on stdout.
+    Json,
+    /// Tab-separated fields, one record per line, no decoration.
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warn,
+    Err,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Warn => "WARN",
+            Self::Err => "ERR",
+        }
+    }
+}
+
+/// One diagnostic result; `id` is stable for scripts, `message` is not.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub id: String,
+    pub status: Status,
+    pub message: String,
+}
+
+/// Checks collected by `doctor` and `sub-info` before printing.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn push(&mut self, status: Status, id: impl Into<String>, message: impl Into<String>) {
+        self.checks.push(Check {
+            id: id.into(),
+            status,
+            message: message.into(),
+        });
+    }
+
+    pub fn ok(&mut self, id: impl Into<String>, message: impl Into<String>) {
+        self.push(Status::Ok, id, message);
+    }
+
+    pub fn warn(&mut self, id: impl Into<String>, message: impl Into<String>) {
+        self.push(Status::Warn, id, message);
+    }
+
+    pub fn err(&mut self, id: impl Into<String>, message: impl Into<String>) {
+        self.push(Status::Err, id, message);
+    }
+
+    pub fn print(&self, output: OutputFormat) -> Result<()> {
+        match output {
+            OutputFormat::Table => {
+                for check in &self.checks {
+                    println!("[{}] {}", check.status.label(), check.message);
+                }
+            }
+            OutputFormat::Plain => {
+                for check in &self.checks {
+                    println!(
+                        "{}\t{}\t{}",
+                        check.status.label().to_lowercase(),
+                        check.id,
+                        check.message
+                    );
+                }
+            }
+            OutputFormat::Json => print_json(&self.checks)?,
+        }
+        Ok(())
+    }
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).context("Failed to serialize output")?;
+    println!("{json}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Report;
+
+    #[test]
+    fn serializes_checks_with_stable_fields() {
+        let mut report = Report::default();
+        report.ok("config", "config path: /tmp/route");
+        report.warn("subscription.main.cache", "no cache");
+
+        let json = serde_json::to_value(&report.checks).unwrap();
+        assert_eq!(json[0]["id"], "config");
+        assert_eq!(json[0]["status"], "ok");
+        assert_eq!(json[1]["status"], "warn");
+        assert_eq!(json[1]["message"], "no cache");
+    }
+}
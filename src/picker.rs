@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use anyhow::{Context, Result, bail};
+use dialoguer::FuzzySelect;
+use dialoguer::theme::ColorfulTheme;
+
+use crate::latency::{LatencyRecord, node_latency};
+use crate::region::{node_region, region_rank};
+use crate::subscription::ProxyNode;
+
+/// Picker rows: nodes grouped by region in `run` priority order, each label
+/// led by its region code so typing `sg` narrows to Singapore.
+fn picker_items<'a>(
+    nodes: &[&'a ProxyNode],
+    latencies: &BTreeMap<String, LatencyRecord>,
+) -> Vec<(&'a ProxyNode, String)> {
+    let mut sorted = nodes.to_vec();
+    // Stable sort keeps subscription order within a region.
+    sorted.sort_by_key(|n| region_rank(&n.name));
+    sorted
+        .into_iter()
+        .map(|node| {
+            let region = node_region(&node.name).unwrap_or("--").to_uppercase();
+            let latency = match node_latency(latencies, node) {
+                Some(LatencyRecord { ms: Some(ms), .. }) => format!("  {ms} ms"),
+                Some(LatencyRecord { ms: None, .. }) => "  timeout".to_string(),
+                None => String::new(),
+            };
+            (node, format!("{region:<3} {}{latency}", node.name))
+        })
+        .collect()
+}
+
+/// Lets the user fuzzy-search `nodes` in the terminal. `None` when the
+/// picker was cancelled with Esc or `q`.
+pub fn pick_node<'a>(
+    nodes: &[&'a ProxyNode],
+    latencies: &BTreeMap<String, LatencyRecord>,
+    selected: Option<&str>,
+) -> Result<Option<&'a ProxyNode>> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        bail!("No terminal for the node picker; pass a node name: route-cli use-node <NODE_NAME>");
+    }
+    if nodes.is_empty() {
+        bail!("No supported node found.");
+    }
+    let items = picker_items(nodes, latencies);
+    let labels: Vec<&str> = items.iter().map(|(_, label)| label.as_str()).collect();
+    let default = selected
+        .and_then(|name| items.iter().position(|(n, _)| n.name == name))
+        .unwrap_or(0);
+    let choice = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select node (type to search, Esc to cancel)")
+        .items(&labels)
+        .default(default)
+        .max_length(15)
+        .interact_opt()
+        .context("Node picker failed")?;
+    Ok(choice.map(|idx| items[idx].0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::picker_items;
+    use crate::latency::LatencyRecord;
+    use crate::subscription::ProxyNode;
+
+    #[test]
+    fn groups_nodes_by_region_with_latency() {
+        let nodes: Vec<ProxyNode> = ["Frankfurt", "🇺🇸 US 01", "🇸🇬 SG 01", "🇺🇸 US 02"]
+            .iter()
+            .map(|name| ProxyNode {
+                name: name.to_string(),
+                server: Some(format!("{name}.example")),
+                ..ProxyNode::default()
+            })
+            .collect();
+        let refs: Vec<&ProxyNode> = nodes.iter().collect();
+        let mut latencies = BTreeMap::new();
+        latencies.insert(
+            nodes[2].fingerprint(),
+            LatencyRecord {
+                ms: Some(42),
+                at: 1,
+            },
+        );
+        latencies.insert(nodes[0].fingerprint(), LatencyRecord { ms: None, at: 1 });
+
+        let labels: Vec<String> = picker_items(&refs, &latencies)
+            .into_iter()
+            .map(|(_, label)| label)
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "SG  🇸🇬 SG 01  42 ms",
+                "US  🇺🇸 US 01",
+                "US  🇺🇸 US 02",
+                "--  Frankfurt  timeout",
+            ]
+        );
+    }
+}
@@ -1,153 +1,114 @@
-use std::fs;
 use std::net::TcpStream;
+use std::path::Path;
 use std::process::Stdio;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, bail};
-use serde_json::{Map, Value, json};
 use tokio::process::{Child, Command};
 use tokio::time::sleep;
 
-use crate::config::{AppConfig, AppPaths};
+use crate::backend::{CoreBackend, CoreVersion, Egress, backend_for};
+use crate::chain::Chain;
+use crate::config::{AppConfig, AppPaths, resolve_proxy_core_path, write_private};
+use crate::install::detect_core_version;
 use crate::subscription::ProxyNode;
 
-fn node_to_outbound(node: &ProxyNode) -> Option<Value> {
-    let server = node.server.as_ref()?;
-    let port = node.port?;
-    match node.node_type.as_str() {
-        "socks5" | "socks" => Some(json!({
-            "type": "socks",
-            "tag": "proxy",
-            "server": server,
-            "server_port": port,
-            "username": node.username,
-            "password": node.password
-        })),
-        "http" => Some(json!({
-            "type": "http",
-            "tag": "proxy",
-            "server": server,
-            "server_port": port,
-            "username": node.username,
-            "password": node.password
-        })),
-        "ss" => {
-            let method = node.cipher.as_ref()?;
-            let password = node.password.as_ref()?;
-            if node.plugin.is_some() {
-                return None;
-            }
-            Some(json!({
-                "type": "shadowsocks",
-                "tag": "proxy",
-                "server": server,
-                "server_port": port,
-                "method": method,
-                "password": password
-            }))
-        }
-        "vmess" => {
-            let uuid = node.uuid.as_ref()?;
-            let mut outbound = Map::<String, Value>::new();
-            outbound.insert("type".to_string(), json!("vmess"));
-            outbound.insert("tag".to_string(), json!("proxy"));
-            outbound.insert("server".to_string(), json!(server));
-            outbound.insert("server_port".to_string(), json!(port));
-            outbound.insert("uuid".to_string(), json!(uuid));
-            if let Some(alter_id) = node.alter_id {
-                outbound.insert("alter_id".to_string(), json!(alter_id));
-            }
-            if let Some(cipher) = &node.cipher {
-                outbound.insert("security".to_string(), json!(cipher));
-            }
-
-            if node.tls.unwrap_or(false) {
-                let mut tls = Map::<String, Value>::new();
-                tls.insert("enabled".to_string(), json!(true));
-                if let Some(sni) = node.sni.as_deref().or(node.servername.as_deref()) {
-                    tls.insert("server_name".to_string(), json!(sni));
-                }
-                outbound.insert("tls".to_string(), Value::Object(tls));
-            }
+/// Asks the configured core for its version, falling back to the version
+/// recorded by `install-core` when the binary cannot report it.
+pub async fn resolve_core_version(cfg: &AppConfig) -> Result<CoreVersion> {
+    let backend = backend_for(cfg.proxy_core.kind);
+    let core_path = resolve_proxy_core_path(&cfg.proxy_core);
+    let raw = match detect_core_version(backend, &core_path).await {
+        Some(version) => version,
+        None => cfg.proxy_core.version.clone().with_context(|| {
+            format!("Could not read the version of proxy core {core_path}; run `route-cli doctor`")
+        })?,
+    };
+    let version = CoreVersion::parse(&raw)
+        .with_context(|| format!("Unrecognized {} version '{raw}'", backend.name()))?;
+    version.require(
+        backend.name(),
+        "route-cli's generated config",
+        backend.minimum_version(),
+    )?;
+    Ok(version)
+}
 
-            let network = node.network.as_deref().unwrap_or("tcp");
-            match network {
-                "ws" => {
-                    let mut transport = Map::<String, Value>::new();
-                    transport.insert("type".to_string(), json!("ws"));
-                    if let Some(path) = node.ws_opts.as_ref().and_then(|w| w.path.as_ref()) {
-                        transport.insert("path".to_string(), json!(path));
-                    }
-                    if let Some(headers) = node.ws_opts.as_ref().and_then(|w| w.headers.as_ref()) {
-                        transport.insert("headers".to_string(), json!(headers));
-                    }
-                    outbound.insert("transport".to_string(), Value::Object(transport));
-                }
-                "grpc" => {
-                    let mut transport = Map::<String, Value>::new();
-                    transport.insert("type".to_string(), json!("grpc"));
-                    if let Some(service_name) = node
-                        .grpc_opts
-                        .as_ref()
-                        .and_then(|g| g.grpc_service_name.as_ref())
-                    {
-                        transport.insert("service_name".to_string(), json!(service_name));
-                    }
-                    outbound.insert("transport".to_string(), Value::Object(transport));
-                }
-                "tcp" => {}
-                _ => {}
-            }
+pub fn generate_core_config(
+    cfg: &AppConfig,
+    egress: &Egress,
+    chain: &Chain,
+    paths: &AppPaths,
+    version: CoreVersion,
+) -> Result<()> {
+    let backend = backend_for(cfg.proxy_core.kind);
+    let content =
+        backend.render_config(cfg, egress, chain, cfg.proxy.mixed_port, false, version)?;
+    write_core_config(&paths.core_config(backend), &content, paths)
+}
 
-            Some(Value::Object(outbound))
-        }
-        _ => None,
-    }
+/// Writes a config that sends all traffic through `node` on the bootstrap
+/// port, used to reach download endpoints when direct egress is blocked.
+pub fn generate_bootstrap_config(
+    cfg: &AppConfig,
+    node: &ProxyNode,
+    chain: &Chain,
+    paths: &AppPaths,
+    version: CoreVersion,
+) -> Result<()> {
+    let backend = backend_for(cfg.proxy_core.kind);
+    let content = backend.render_config(
+        cfg,
+        &Egress::Node(node),
+        chain,
+        cfg.network.bootstrap_port,
+        true,
+        version,
+    )?;
+    write_core_config(&paths.bootstrap_config(backend), &content, paths)
 }
 
-pub fn generate_sing_box_config(cfg: &AppConfig, node: &ProxyNode, paths: &AppPaths) -> Result<()> {
-    let outbound = node_to_outbound(node).with_context(|| {
-        format!(
-            "Selected node '{}' with type '{}' is unsupported by this MVP (supports socks5/socks/http/ss/vmess)",
-            node.name, node.node_type
-        )
-    })?;
+fn write_core_config(path: &Path, content: &str, paths: &AppPaths) -> Result<()> {
+    paths.ensure_dirs()?;
+    write_private(path, content)?;
+    Ok(())
+}
 
-    let content = json!({
-        "log": { "level": "warn" },
-        "inbounds": [{
-            "type": "mixed",
-            "tag": "mixed-in",
-            "listen": "127.0.0.1",
-            "listen_port": cfg.proxy.mixed_port
-        }],
-        "outbounds": [
-            outbound,
-            { "type": "direct", "tag": "direct" }
-        ],
-        "route": {
-            "rules": [{
-                "domain_suffix": cfg.routing.proxy_domains,
-                "outbound": "proxy"
-            }],
-            "final": "direct"
+/// Asks the core to validate a generated config so schema errors surface
+/// before anything is launched.
+pub async fn check_core_config(
+    backend: &dyn CoreBackend,
+    core_path: &str,
+    config_path: &Path,
+) -> Result<()> {
+    let output = Command::new(core_path)
+        .args(backend.check_args(config_path))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {core_path} to check its config"))?;
+    if !output.status.success() {
+        let mut details = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if details.is_empty() {
+            details = String::from_utf8_lossy(&output.stdout).trim().to_string();
         }
-    });
-
-    paths.ensure_dirs()?;
-    fs::write(
-        &paths.sing_box_json,
-        serde_json::to_string_pretty(&content).context("Failed to serialize sing-box config")?,
-    )
-    .with_context(|| format!("Failed to write {}", paths.sing_box_json.display()))?;
+        bail!(
+            "{} rejected {}:\n{details}",
+            backend.name(),
+            config_path.display()
+        );
+    }
     Ok(())
 }
 
-pub async fn spawn_proxy_core(core_path: &str, config_path: &str) -> Result<Child> {
+pub async fn spawn_proxy_core(
+    backend: &dyn CoreBackend,
+    core_path: &str,
+    config_path: &Path,
+) -> Result<Child> {
     let child = Command::new(core_path)
-        .arg("run")
-        .arg("-c")
-        .arg(config_path)
+        .args(backend.run_args(config_path))
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
@@ -0,0 +1,88 @@
+/// Regions recognised in node names, in selection priority order: flags and
+/// names found anywhere in the name (case-insensitive), then short codes.
+const REGIONS: [(&str, &[&str], &[&str]); 7] = [
+    ("sg", &["🇸🇬", "新加坡", "singapore"], &["sg"]),
+    ("kr", &["🇰🇷", "韩国", "korea"], &["kr"]),
+    ("us", &["🇺🇸", "美国", "united states"], &["us", "usa"]),
+    ("jp", &["🇯🇵", "日本", "japan"], &["jp"]),
+    ("hk", &["🇭🇰", "香港", "hong kong"], &["hk"]),
+    ("tw", &["🇹🇼", "台湾", "taiwan"], &["tw"]),
+    ("gb", &["🇬🇧", "英国", "united kingdom"], &["uk", "gb"]),
+];
+
+/// Number of regions `run` tries ahead of the rest.
+const PREFERRED_REGIONS: u8 = 3;
+
+/// Codes accepted by `region:<code>` selectors.
+pub fn region_codes() -> impl Iterator<Item = &'static str> {
+    REGIONS.iter().map(|(code, _, _)| *code)
+}
+
+pub fn is_known_region(code: &str) -> bool {
+    region_codes().any(|c| c == code)
+}
+
+/// Region code (`sg`, `us`, ...) a node name points to.
+pub fn node_region(name: &str) -> Option<&'static str> {
+    let lower = name.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .collect();
+    REGIONS
+        .iter()
+        .find(|(_, names, codes)| {
+            names.iter().any(|n| lower.contains(n))
+                || codes
+                    .iter()
+                    .any(|code| tokens.iter().any(|t| is_code_token(t, code)))
+        })
+        .map(|(code, _, _)| *code)
+}
+
+/// Short codes only count as a whole word, optionally numbered (`SG-01`,
+/// `us2`), so `Russia` or `network` do not match.
+fn is_code_token(token: &str, code: &str) -> bool {
+    token
+        .strip_prefix(code)
+        .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Position of a node's region in `REGIONS`; unknown regions sort last.
+pub fn region_rank(name: &str) -> usize {
+    node_region(name)
+        .and_then(|code| REGIONS.iter().position(|(c, _, _)| *c == code))
+        .unwrap_or(REGIONS.len())
+}
+
+/// Order in which `run` pings nodes: Singapore, Korea, United States, then
+/// everything else.
+pub fn region_priority(name: &str) -> u8 {
+    region_rank(name).min(PREFERRED_REGIONS as usize) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{node_region, region_priority};
+
+    #[test]
+    fn detects_regions_from_names() {
+        assert_eq!(node_region("🇸🇬 Singapore 01"), Some("sg"));
+        assert_eq!(node_region("新加坡-02"), Some("sg"));
+        assert_eq!(node_region("JP Tokyo"), Some("jp"));
+        assert_eq!(node_region("Frankfurt"), None);
+        assert_eq!(node_region("SG-01"), Some("sg"));
+        assert_eq!(node_region("us2 fast"), Some("us"));
+        assert_eq!(node_region("美国US01"), Some("us"));
+        assert_eq!(node_region("UK London"), Some("gb"));
+        assert_eq!(node_region("Russia 01"), None);
+        assert_eq!(node_region("Australia"), None);
+        assert_eq!(node_region("Belarus"), None);
+        assert_eq!(node_region("Ukraine"), None);
+        assert_eq!(node_region("network test"), None);
+        assert_eq!(node_region("Busan"), None);
+        assert!(region_priority("SG 01") < region_priority("KR 01"));
+        assert!(region_priority("US 01") < region_priority("Tokyo JP"));
+        assert_eq!(region_priority("Tokyo JP"), region_priority("Frankfurt"));
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::{Result, bail};
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::config::RuntimeConfig;
+use crate::region::{is_known_region, node_region, region_codes};
+use crate::subscription::ProxyNode;
+
+/// Candidates listed in an ambiguity error before it is cut short.
+const MAX_LISTED: usize = 10;
+
+/// Resolves a `use-node` / `run --node` selector against the node pool:
+///
+/// - `#12`: the 12th node as numbered by `list-nodes`
+/// - `region:sg`: every node in that region
+/// - anything else: the exact name, else the only case-insensitive substring
+///   match, else the best-scoring fuzzy match
+///
+/// Name selectors yield exactly one node; several matches are an error
+/// listing the candidates.
+pub fn select_nodes<'a>(nodes: &'a [ProxyNode], selector: &str) -> Result<Vec<&'a ProxyNode>> {
+    if let Some(index) = selector.strip_prefix('#')
+        && let Ok(index) = index.parse::<usize>()
+    {
+        return match index.checked_sub(1).and_then(|i| nodes.get(i)) {
+            Some(node) => Ok(vec![node]),
+            None => bail!(
+                "Node index #{index} is out of range; `route-cli list-nodes` shows {} nodes",
+                nodes.len()
+            ),
+        };
+    }
+    if let Some(code) = selector.strip_prefix("region:") {
+        let code = code.to_lowercase();
+        if !is_known_region(&code) {
+            bail!(
+                "Unknown region '{code}'; known regions: {}",
+                region_codes().collect::<Vec<_>>().join(", ")
+            );
+        }
+        let matched: Vec<&ProxyNode> = nodes
+            .iter()
+            .filter(|n| node_region(&n.name) == Some(code.as_str()))
+            .collect();
+        if matched.is_empty() {
+            bail!("No node found in region '{code}'");
+        }
+        return Ok(matched);
+    }
+
+    if let Some(node) = nodes.iter().find(|n| n.name == selector) {
+        return Ok(vec![node]);
+    }
+    let needle = selector.to_lowercase();
+    let substring: Vec<&ProxyNode> = nodes
+        .iter()
+        .filter(|n| n.name.to_lowercase().contains(&needle))
+        .collect();
+    let matched = if substring.is_empty() {
+        best_fuzzy_matches(nodes, selector)
+    } else {
+        substring
+    };
+    match matched.len() {
+        0 => bail!("Node '{selector}' not found in cached subscription"),
+        1 => Ok(matched),
+        count => bail!(
+            "Node selector '{selector}' matches {count} nodes:\n{}\nUse a longer name or `#<index>` from `route-cli list-nodes`",
+            candidate_list(nodes, &matched)
+        ),
+    }
+}
+
+/// Fuzzy matches sharing the best score. Near matches are common in provider
+/// names (`US 01`, `US 02`), so only a tie at the top is ambiguous.
+fn best_fuzzy_matches<'a>(nodes: &'a [ProxyNode], selector: &str) -> Vec<&'a ProxyNode> {
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let scored: Vec<(i64, &ProxyNode)> = nodes
+        .iter()
+        .filter_map(|n| Some((matcher.fuzzy_match(&n.name, selector)?, n)))
+        .collect();
+    let Some(best) = scored.iter().map(|(score, _)| *score).max() else {
+        return Vec::new();
+    };
+    scored
+        .into_iter()
+        .filter(|(score, _)| *score == best)
+        .map(|(_, n)| n)
+        .collect()
+}
+
+/// The saved `runtime.selected_node` in `nodes`: by fingerprint first, so a
+/// node the provider renamed is still found, then by name.
+pub fn resolve_selected<'a, I>(runtime: &RuntimeConfig, nodes: I) -> Option<&'a ProxyNode>
+where
+    I: IntoIterator<Item = &'a ProxyNode>,
+    I::IntoIter: Clone,
+{
+    let name = runtime.selected_node.as_deref()?;
+    let mut nodes = nodes.into_iter();
+    let by_fingerprint = runtime
+        .selected_fingerprint
+        .as_deref()
+        .and_then(|fingerprint| {
+            let matches: Vec<&ProxyNode> = nodes
+                .clone()
+                .filter(|n| n.fingerprint() == fingerprint)
+                .collect();
+            matches
+                .iter()
+                .find(|n| n.name == name)
+                .or(matches.first())
+                .copied()
+        });
+    by_fingerprint.or_else(|| nodes.find(|n| n.name == name))
+}
+
+fn candidate_list(nodes: &[ProxyNode], matched: &[&ProxyNode]) -> String {
+    let mut lines: Vec<String> = matched
+        .iter()
+        .take(MAX_LISTED)
+        .map(|m| {
+            let index = nodes.iter().position(|n| std::ptr::eq(n, *m)).unwrap_or(0);
+            format!("  #{:03} {}", index + 1, m.name)
+        })
+        .collect();
+    if matched.len() > MAX_LISTED {
+        lines.push(format!("  ... and {} more", matched.len() - MAX_LISTED));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_selected, select_nodes};
+    use crate::config::AppConfig;
+    use crate::subscription::ProxyNode;
+
+    fn pool() -> Vec<ProxyNode> {
+        [
+            "🇸🇬 Singapore 01",
+            "🇸🇬 Singapore 02",
+            "🇺🇸 Los Angeles",
+            "Frankfurt",
+        ]
+        .iter()
+        .map(|name| ProxyNode {
+            name: name.to_string(),
+            ..ProxyNode::default()
+        })
+        .collect()
+    }
+
+    fn names(nodes: Vec<&ProxyNode>) -> Vec<&str> {
+        nodes.into_iter().map(|n| n.name.as_str()).collect()
+    }
+
+    #[test]
+    fn selects_by_index_region_and_name() {
+        let pool = pool();
+        assert_eq!(
+            names(select_nodes(&pool, "#3").unwrap()),
+            ["🇺🇸 Los Angeles"]
+        );
+        assert!(select_nodes(&pool, "#0").is_err());
+        assert!(select_nodes(&pool, "#9").is_err());
+        assert_eq!(
+            names(select_nodes(&pool, "region:SG").unwrap()),
+            ["🇸🇬 Singapore 01", "🇸🇬 Singapore 02"]
+        );
+        assert!(select_nodes(&pool, "region:mars").is_err());
+        assert_eq!(
+            names(select_nodes(&pool, "singapore 02").unwrap()),
+            ["🇸🇬 Singapore 02"]
+        );
+        assert_eq!(names(select_nodes(&pool, "frnkf").unwrap()), ["Frankfurt"]);
+        assert!(select_nodes(&pool, "tokyo").is_err());
+    }
+
+    #[test]
+    fn lists_candidates_when_ambiguous() {
+        let err = select_nodes(&pool(), "singapore").unwrap_err().to_string();
+        assert!(err.contains("matches 2 nodes"), "{err}");
+        assert!(err.contains("#001 🇸🇬 Singapore 01"), "{err}");
+        assert!(err.contains("#002 🇸🇬 Singapore 02"), "{err}");
+    }
+
+    #[test]
+    fn picks_the_best_of_several_fuzzy_matches() {
+        let pool: Vec<ProxyNode> = ["US 01 Seattle", "US 02 San Jose", "US 03 Los Angeles"]
+            .iter()
+            .map(|name| ProxyNode {
+                name: name.to_string(),
+                ..ProxyNode::default()
+            })
+            .collect();
+        assert_eq!(
+            names(select_nodes(&pool, "us sea").unwrap()),
+            ["US 01 Seattle"]
+        );
+        assert_eq!(
+            names(select_nodes(&pool, "usjose").unwrap()),
+            ["US 02 San Jose"]
+        );
+
+        let err = select_nodes(&pool, "u0").unwrap_err().to_string();
+        assert!(err.contains("matches 3 nodes"), "{err}");
+    }
+
+    #[test]
+    fn follows_renamed_selection_by_fingerprint() {
+        let mut pool: Vec<ProxyNode> = serde_yaml::from_str(
+            "[{ name: '🇸🇬 Singapore 01', type: ss, server: sg.example, port: 8388, password: a }, { name: JP, type: ss, server: jp.example, port: 8388, password: b }]",
+        )
+        .unwrap();
+        let mut cfg = AppConfig::default();
+        cfg.runtime.select_node(&pool[0]);
+
+        pool[0].name = "SG-01 x1.0".to_string();
+        let found = resolve_selected(&cfg.runtime, &pool).unwrap();
+        assert_eq!(found.name, "SG-01 x1.0");
+        assert!(!cfg.runtime.is_selected(found));
+
+        pool[0].password = Some("rotated".to_string());
+        assert!(resolve_selected(&cfg.runtime, &pool).is_none());
+    }
+}
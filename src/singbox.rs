@@ -0,0 +1,877 @@
+use std::ffi::OsString;
+use std::net::IpAddr;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value, json};
+
+use crate::backend::{CoreBackend, CoreVersion, Egress, Unsupported, host_labels, required};
+use crate::chain::Chain;
+use crate::config::AppConfig;
+use crate::groups::{GroupKind, RuleMatch};
+use crate::subscription::ProxyNode;
+
+const BINARY: &str = if cfg!(windows) {
+    "sing-box.exe"
+} else {
+    "sing-box"
+};
+/// Inbound `sniff` fields were replaced by the `sniff` route rule action.
+const RULE_ACTIONS: CoreVersion = CoreVersion::new(1, 11, 0);
+/// The WireGuard outbound was deprecated in favour of an endpoint.
+const WIREGUARD_ENDPOINTS: CoreVersion = CoreVersion::new(1, 11, 0);
+/// DNS servers became typed objects instead of `address` URLs.
+const TYPED_DNS_SERVERS: CoreVersion = CoreVersion::new(1, 12, 0);
+
+pub struct SingBox;
+
+impl CoreBackend for SingBox {
+    fn name(&self) -> &'static str {
+        "sing-box"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        BINARY
+    }
+
+    fn config_extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn minimum_version(&self) -> CoreVersion {
+        CoreVersion::new(1, 8, 0)
+    }
+
+    fn check(&self, node: &ProxyNode) -> Result<(), Unsupported> {
+        node_to_outbound(node).map(|_| ())
+    }
+
+    fn render_config(
+        &self,
+        cfg: &AppConfig,
+        egress: &Egress,
+        chain: &Chain,
+        listen_port: u16,
+        route_all: bool,
+        version: CoreVersion,
+    ) -> Result<String> {
+        let content = render_sing_box_config(cfg, egress, chain, listen_port, route_all, version)?;
+        serde_json::to_string_pretty(&content).context("Failed to serialize sing-box config")
+    }
+
+    fn run_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec!["run".into(), "-c".into(), config_path.into()]
+    }
+
+    fn check_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec!["check".into(), "-c".into(), config_path.into()]
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        &["version"]
+    }
+
+    fn release_repo(&self) -> &'static str {
+        "SagerNet/sing-box"
+    }
+
+    fn host_asset_name(&self, version: &str) -> Result<String> {
+        let (os, arch) = host_labels(
+            self.name(),
+            ["windows", "linux", "darwin"],
+            ["amd64", "arm64", "386"],
+        )?;
+        let ext = if os == "windows" { "zip" } else { "tar.gz" };
+        Ok(format!("sing-box-{version}-{os}-{arch}.{ext}"))
+    }
+}
+
+/// The `tls` object for nodes with TLS enabled.
+fn tls_options(node: &ProxyNode) -> Option<Value> {
+    if !node.tls.unwrap_or(false) {
+        return None;
+    }
+    let mut tls = json!({ "enabled": true });
+    if let Some(sni) = node.sni.as_deref().or(node.servername.as_deref()) {
+        tls["server_name"] = json!(sni);
+    }
+    if node.skip_cert_verify.unwrap_or(false) {
+        tls["insecure"] = json!(true);
+    }
+    if let Some(alpn) = node.alpn.as_ref().filter(|a| !a.is_empty()) {
+        tls["alpn"] = json!(alpn);
+    }
+    if let Some(fingerprint) = &node.client_fingerprint {
+        tls["utls"] = json!({ "enabled": true, "fingerprint": fingerprint });
+    }
+    Some(tls)
+}
+
+fn node_to_outbound(node: &ProxyNode) -> Result<Value, Unsupported> {
+    let server = required(node.server.as_ref(), "server")?;
+    let port = required(node.port, "port")?;
+    // sing-box cannot pin a certificate hash; refusing the node beats
+    // silently dropping the pin.
+    if node.fingerprint.is_some() {
+        return Err(Unsupported::Feature("certificate pinning (`fingerprint`)"));
+    }
+    match node.node_type.as_str() {
+        // sing-box's socks outbound has no TLS.
+        "socks5" | "socks" if node.tls.unwrap_or(false) => {
+            Err(Unsupported::Feature("TLS on socks5"))
+        }
+        "socks5" | "socks" => Ok(json!({
+            "type": "socks",
+            "tag": "proxy",
+            "server": server,
+            "server_port": port,
+            "username": node.username,
+            "password": node.password
+        })),
+        "http" => {
+            let mut outbound = json!({
+                "type": "http",
+                "tag": "proxy",
+                "server": server,
+                "server_port": port,
+                "username": node.username,
+                "password": node.password
+            });
+            if let Some(tls) = tls_options(node) {
+                outbound["tls"] = tls;
+            }
+            Ok(outbound)
+        }
+        "ss" => {
+            let method = required(node.cipher.as_ref(), "cipher")?;
+            let password = required(node.password.as_ref(), "password")?;
+            if let Some(plugin) = &node.plugin {
+                return Err(Unsupported::Plugin(plugin.clone()));
+            }
+            Ok(json!({
+                "type": "shadowsocks",
+                "tag": "proxy",
+                "server": server,
+                "server_port": port,
+                "method": method,
+                "password": password
+            }))
+        }
+        "vmess" => {
+            let uuid = required(node.uuid.as_ref(), "uuid")?;
+            let mut outbound = Map::<String, Value>::new();
+            outbound.insert("type".to_string(), json!("vmess"));
+            outbound.insert("tag".to_string(), json!("proxy"));
+            outbound.insert("server".to_string(), json!(server));
+            outbound.insert("server_port".to_string(), json!(port));
+            outbound.insert("uuid".to_string(), json!(uuid));
+            if let Some(alter_id) = node.alter_id {
+                outbound.insert("alter_id".to_string(), json!(alter_id));
+            }
+            if let Some(cipher) = &node.cipher {
+                outbound.insert("security".to_string(), json!(cipher));
+            }
+
+            if let Some(tls) = tls_options(node) {
+                outbound.insert("tls".to_string(), tls);
+            }
+
+            if let Some(transport) = v2ray_transport(node)? {
+                outbound.insert("transport".to_string(), transport);
+            }
+
+            Ok(Value::Object(outbound))
+        }
+        "wireguard" => {
+            let mut outbound = json!({
+                "type": "wireguard",
+                "tag": "proxy",
+                "server": server,
+                "server_port": port,
+                "local_address": wireguard_addresses(node)?,
+                "private_key": required(node.extra_str("private-key"), "private-key")?,
+                "peer_public_key": required(node.extra_str("public-key"), "public-key")?
+            });
+            if let Some(psk) = node.extra_str("pre-shared-key") {
+                outbound["pre_shared_key"] = json!(psk);
+            }
+            // Either `[1, 2, 3]` or base64; sing-box reads both.
+            if let Some(reserved) = node.extra.get("reserved") {
+                outbound["reserved"] = json!(reserved);
+            }
+            if let Some(mtu) = node.extra.get("mtu").and_then(|m| m.as_u64()) {
+                outbound["mtu"] = json!(mtu);
+            }
+            Ok(outbound)
+        }
+        "ssh" => {
+            let mut outbound = json!({
+                "type": "ssh",
+                "tag": "proxy",
+                "server": server,
+                "server_port": port
+            });
+            if let Some(user) = &node.username {
+                outbound["user"] = json!(user);
+            }
+            if let Some(password) = &node.password {
+                outbound["password"] = json!(password);
+            }
+            // Clash accepts the key itself or a path to it.
+            if let Some(key) = node.extra_str("private-key") {
+                let field = if key.contains("PRIVATE KEY") {
+                    "private_key"
+                } else {
+                    "private_key_path"
+                };
+                outbound[field] = json!(key);
+            }
+            if let Some(passphrase) = node.extra_str("private-key-passphrase") {
+                outbound["private_key_passphrase"] = json!(passphrase);
+            }
+            for (clash_key, field) in [
+                ("host-key", "host_key"),
+                ("host-key-algorithms", "host_key_algorithms"),
+            ] {
+                if let Some(values) = node.extra.get(clash_key) {
+                    outbound[field] = json!(values);
+                }
+            }
+            Ok(outbound)
+        }
+        other => Err(Unsupported::Type(other.to_string())),
+    }
+}
+
+/// The V2Ray transport for the node's `network`, `None` for plain TCP.
+/// Transports sing-box cannot speak make the node unsupported rather than a
+/// TCP outbound that never connects.
+fn v2ray_transport(node: &ProxyNode) -> Result<Option<Value>, Unsupported> {
+    let network = node.network.as_deref().unwrap_or("tcp");
+    let ws = node.ws_opts.as_ref();
+    let transport = match network {
+        "tcp" => return Ok(None),
+        "httpupgrade" | "ws"
+            if network == "httpupgrade" || ws.and_then(|w| w.v2ray_http_upgrade) == Some(true) =>
+        {
+            let mut transport = json!({ "type": "httpupgrade" });
+            if let Some(path) = ws.and_then(|w| w.path.as_ref()) {
+                transport["path"] = json!(path);
+            }
+            if let Some(headers) = ws.and_then(|w| w.headers.as_ref()) {
+                let mut headers = headers.clone();
+                if let Some(host) = headers.remove("Host") {
+                    transport["host"] = json!(host);
+                }
+                if !headers.is_empty() {
+                    transport["headers"] = json!(headers);
+                }
+            }
+            transport
+        }
+        "ws" => {
+            let mut transport = json!({ "type": "ws" });
+            if let Some(ws) = ws {
+                if let Some(path) = &ws.path {
+                    transport["path"] = json!(path);
+                }
+                if let Some(headers) = &ws.headers {
+                    transport["headers"] = json!(headers);
+                }
+                if let Some(max_early_data) = ws.max_early_data {
+                    transport["max_early_data"] = json!(max_early_data);
+                }
+                if let Some(header) = &ws.early_data_header_name {
+                    transport["early_data_header_name"] = json!(header);
+                }
+            }
+            transport
+        }
+        // Clash only speaks h2 over TLS; without it sing-box would fall back
+        // to HTTP/1.1.
+        "h2" if node.tls.unwrap_or(false) => {
+            let mut transport = json!({ "type": "http" });
+            if let Some(h2) = &node.h2_opts {
+                if let Some(host) = &h2.host {
+                    transport["host"] = json!(host);
+                }
+                if let Some(path) = &h2.path {
+                    transport["path"] = json!(path);
+                }
+            }
+            transport
+        }
+        "grpc" => {
+            let mut transport = json!({ "type": "grpc" });
+            if let Some(service_name) = node
+                .grpc_opts
+                .as_ref()
+                .and_then(|g| g.grpc_service_name.as_ref())
+            {
+                transport["service_name"] = json!(service_name);
+            }
+            transport
+        }
+        // `http` is V2Ray's HTTP header obfuscation over TCP, which sing-box
+        // has no transport for.
+        other => return Err(Unsupported::Transport(other.to_string())),
+    };
+    Ok(Some(transport))
+}
+
+/// Interface addresses from Clash's `ip` and `ipv6`, as prefixes.
+fn wireguard_addresses(node: &ProxyNode) -> Result<Vec<String>, Unsupported> {
+    let mut addresses = vec![with_prefix(required(node.extra_str("ip"), "ip")?, 32)];
+    if let Some(ipv6) = node.extra_str("ipv6") {
+        addresses.push(with_prefix(ipv6, 128));
+    }
+    Ok(addresses)
+}
+
+fn with_prefix(address: &str, bits: u8) -> String {
+    if address.contains('/') {
+        address.to_string()
+    } else {
+        format!("{address}/{bits}")
+    }
+}
+
+/// Rewrites a WireGuard outbound as the endpoint newer cores expect.
+fn wireguard_endpoint(outbound: &Value) -> Value {
+    let mut peer = json!({
+        "address": outbound["server"],
+        "port": outbound["server_port"],
+        "public_key": outbound["peer_public_key"],
+        "allowed_ips": ["0.0.0.0/0", "::/0"]
+    });
+    for key in ["pre_shared_key", "reserved"] {
+        if let Some(value) = outbound.get(key) {
+            peer[key] = value.clone();
+        }
+    }
+    let mut endpoint = json!({
+        "type": "wireguard",
+        "tag": outbound["tag"],
+        "address": outbound["local_address"],
+        "private_key": outbound["private_key"],
+        "peers": [peer]
+    });
+    for key in ["mtu", "detour"] {
+        if let Some(value) = outbound.get(key) {
+            endpoint[key] = value.clone();
+        }
+    }
+    endpoint
+}
+
+/// Remote rule sets from `routing.rule_sets`, downloaded through the proxy.
+/// They replace the geosite/geoip databases, which 1.12 removed.
+fn rule_sets(urls: &[String]) -> Result<Vec<Value>> {
+    urls.iter()
+        .enumerate()
+        .map(|(i, url)| {
+            let path = reqwest::Url::parse(url)
+                .with_context(|| format!("Invalid rule set URL '{url}'"))?
+                .path()
+                .to_string();
+            let format = if path.ends_with(".srs") {
+                "binary"
+            } else if path.ends_with(".json") {
+                "source"
+            } else {
+                bail!("Rule set '{url}' must be a .srs or .json file");
+            };
+            Ok(json!({
+                "type": "remote",
+                "tag": format!("rule-set-{}", i + 1),
+                "format": format,
+                "url": url,
+                "download_detour": "proxy"
+            }))
+        })
+        .collect()
+}
+
+/// The `dns` block for `routing.dns` (`1.1.1.1`, `tls://1.1.1.1`,
+/// `https://dns.google/dns-query`, ...), queried through the proxy.
+fn dns_config(server: &str, version: CoreVersion) -> Result<Value> {
+    let url = if server.contains("://") {
+        server.to_string()
+    } else {
+        format!("udp://{server}")
+    };
+    let parsed =
+        reqwest::Url::parse(&url).with_context(|| format!("Invalid routing.dns '{server}'"))?;
+    let scheme = parsed.scheme();
+    if !["udp", "tcp", "tls", "quic", "https", "h3"].contains(&scheme) {
+        bail!("routing.dns scheme must be udp, tcp, tls, quic, https or h3, not '{scheme}'");
+    }
+    let host = parsed
+        .host_str()
+        .with_context(|| format!("routing.dns '{server}' has no host"))?;
+    let by_name = host.trim_matches(['[', ']']).parse::<IpAddr>().is_err();
+
+    let mut servers = Vec::new();
+    if version >= TYPED_DNS_SERVERS {
+        let mut remote = json!({
+            "type": scheme,
+            "tag": "remote",
+            "server": host.trim_matches(['[', ']']),
+            "detour": "proxy"
+        });
+        if let Some(port) = parsed.port() {
+            remote["server_port"] = json!(port);
+        }
+        if matches!(scheme, "https" | "h3") && parsed.path() != "/dns-query" {
+            remote["path"] = json!(parsed.path());
+        }
+        if by_name {
+            remote["domain_resolver"] = json!("local");
+            servers.push(remote);
+            servers.push(json!({ "type": "local", "tag": "local" }));
+        } else {
+            servers.push(remote);
+        }
+    } else {
+        // Legacy servers are URLs; plain UDP is the bare address.
+        let address = if scheme == "udp" {
+            server
+        } else {
+            url.as_str()
+        };
+        let mut remote = json!({ "tag": "remote", "address": address, "detour": "proxy" });
+        if by_name {
+            remote["address_resolver"] = json!("local");
+            servers.push(remote);
+            servers.push(json!({ "tag": "local", "address": "local" }));
+        } else {
+            servers.push(remote);
+        }
+    }
+    Ok(json!({ "servers": servers, "final": "remote" }))
+}
+
+/// Outbounds for `egress`; the one traffic is routed to is tagged `proxy`.
+/// Node outbounds are dialed through `detour` when set.
+fn egress_outbounds(egress: &Egress, detour: Option<&str>) -> Result<Vec<Value>> {
+    let (group, members) = match egress {
+        Egress::Node(node) => {
+            let mut outbound = node_to_outbound(node).with_context(|| {
+                format!("Selected node '{}' is unsupported by sing-box", node.name)
+            })?;
+            if let Some(detour) = detour {
+                outbound["detour"] = json!(detour);
+            }
+            return Ok(vec![outbound]);
+        }
+        Egress::Group(group, members) => (group, members),
+    };
+
+    let mut outbounds = Vec::new();
+    for node in members {
+        if let Ok(mut outbound) = node_to_outbound(node) {
+            outbound["tag"] = json!(format!("proxy-{}", outbounds.len() + 1));
+            if let Some(detour) = detour {
+                outbound["detour"] = json!(detour);
+            }
+            outbounds.push(outbound);
+        }
+    }
+    if outbounds.is_empty() {
+        bail!("Group '{}' has no node supported by sing-box", group.name);
+    }
+    let tags: Vec<Value> = outbounds.iter().map(|o| o["tag"].clone()).collect();
+
+    let group_outbound = match group.kind {
+        GroupKind::Select => json!({
+            "type": "selector",
+            "tag": "proxy",
+            "outbounds": tags,
+            "default": tags[0]
+        }),
+        // sing-box has no fallback or load-balance group; urltest keeps
+        // traffic on the fastest healthy member, which covers both.
+        GroupKind::UrlTest | GroupKind::Fallback | GroupKind::LoadBalance => {
+            let mut urltest = json!({ "type": "urltest", "tag": "proxy", "outbounds": tags });
+            if let Some(url) = &group.url {
+                urltest["url"] = json!(url);
+            }
+            if let Some(interval) = group.interval {
+                urltest["interval"] = json!(format!("{interval}s"));
+            }
+            if let Some(tolerance) = group.tolerance {
+                urltest["tolerance"] = json!(tolerance);
+            }
+            urltest
+        }
+    };
+    outbounds.push(group_outbound);
+    Ok(outbounds)
+}
+
+/// Outbounds for the chain, each dialed through the previous one, and the
+/// tag of the last one.
+fn chain_outbounds(chain: &Chain) -> Result<(Vec<Value>, Option<String>)> {
+    let mut outbounds = Vec::new();
+    let mut detour: Option<String> = None;
+    for (i, node) in chain.links().into_iter().enumerate() {
+        let mut outbound = node_to_outbound(node)
+            .with_context(|| format!("Chain hop '{}' is unsupported by sing-box", node.name))?;
+        let tag = format!("chain-{}", i + 1);
+        outbound["tag"] = json!(tag);
+        if let Some(previous) = detour {
+            outbound["detour"] = json!(previous);
+        }
+        outbounds.push(outbound);
+        detour = Some(tag);
+    }
+    Ok((outbounds, detour))
+}
+
+fn rule_field(kind: RuleMatch) -> &'static str {
+    match kind {
+        RuleMatch::Domain => "domain",
+        RuleMatch::DomainSuffix => "domain_suffix",
+        RuleMatch::DomainKeyword => "domain_keyword",
+        RuleMatch::IpCidr => "ip_cidr",
+    }
+}
+
+fn render_sing_box_config(
+    cfg: &AppConfig,
+    egress: &Egress,
+    chain: &Chain,
+    listen_port: u16,
+    route_all: bool,
+    version: CoreVersion,
+) -> Result<Value> {
+    let (chain_outbounds, detour) = chain_outbounds(chain)?;
+    let mut outbounds = egress_outbounds(egress, detour.as_deref())?;
+    outbounds.extend(chain_outbounds);
+    outbounds.push(json!({ "type": "direct", "tag": "direct" }));
+    let (endpoints, outbounds): (Vec<Value>, Vec<Value>) = outbounds
+        .into_iter()
+        .partition(|o| version >= WIREGUARD_ENDPOINTS && o["type"] == "wireguard");
+
+    let mut inbound = json!({
+        "type": "mixed",
+        "tag": "mixed-in",
+        "listen": "127.0.0.1",
+        "listen_port": listen_port
+    });
+    // Sniffing lets domain rules match clients that connect by IP.
+    let mut rules = Vec::new();
+    if version >= RULE_ACTIONS {
+        rules.push(json!({ "inbound": "mixed-in", "action": "sniff" }));
+    } else {
+        inbound["sniff"] = json!(true);
+    }
+    for rule in egress.provider_rules(cfg) {
+        rules.push(json!({ rule_field(rule.kind): [rule.value], "outbound": "proxy" }));
+    }
+
+    let rule_sets = rule_sets(&cfg.routing.rule_sets)?;
+    let route = if route_all {
+        json!({ "rules": rules, "final": "proxy" })
+    } else {
+        if !rule_sets.is_empty() {
+            let tags: Vec<&Value> = rule_sets.iter().map(|r| &r["tag"]).collect();
+            rules.push(json!({ "rule_set": tags, "outbound": "proxy" }));
+        }
+        rules.push(json!({
+            "domain_suffix": cfg.routing.proxy_domains,
+            "outbound": "proxy"
+        }));
+        json!({ "rules": rules, "final": "direct" })
+    };
+
+    let mut config = json!({
+        "log": { "level": "warn" },
+        "inbounds": [inbound],
+        "outbounds": outbounds,
+        "route": route
+    });
+    if !rule_sets.is_empty() {
+        config["route"]["rule_set"] = json!(rule_sets);
+    }
+    if let Some(server) = &cfg.routing.dns {
+        config["dns"] = dns_config(server, version)?;
+    }
+    if !endpoints.is_empty() {
+        config["endpoints"] = endpoints.iter().map(wireguard_endpoint).collect();
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SingBox, render_sing_box_config};
+    use crate::backend::{CoreBackend, CoreVersion, Egress, Unsupported};
+    use crate::chain::Chain;
+    use crate::config::AppConfig;
+    use crate::groups::{GroupKind, ProviderGroup, ProviderRule, RuleMatch};
+    use crate::subscription::ProxyNode;
+
+    fn socks_node() -> ProxyNode {
+        serde_yaml::from_str("{ name: n, type: socks5, server: 1.2.3.4, port: 1080 }").unwrap()
+    }
+
+    #[test]
+    fn sniffing_follows_core_schema() {
+        let cfg = AppConfig::default();
+        let node = socks_node();
+
+        let legacy = render_sing_box_config(
+            &cfg,
+            &Egress::Node(&node),
+            &Chain::default(),
+            27890,
+            false,
+            CoreVersion::new(1, 10, 0),
+        )
+        .unwrap();
+        assert_eq!(legacy["inbounds"][0]["sniff"], true);
+        assert_eq!(legacy["route"]["rules"][0]["outbound"], "proxy");
+
+        let current = render_sing_box_config(
+            &cfg,
+            &Egress::Node(&node),
+            &Chain::default(),
+            27890,
+            false,
+            CoreVersion::new(1, 11, 0),
+        )
+        .unwrap();
+        assert!(current["inbounds"][0].get("sniff").is_none());
+        assert_eq!(current["route"]["rules"][0]["action"], "sniff");
+        assert_eq!(current["route"]["rules"][1]["outbound"], "proxy");
+    }
+
+    #[test]
+    fn renders_provider_group_as_urltest() {
+        let mut cfg = AppConfig::default();
+        cfg.routing.provider_rules = true;
+        let node = socks_node();
+        let group = ProviderGroup {
+            name: "OpenAI".to_string(),
+            kind: GroupKind::Fallback,
+            members: vec![node.name.clone()],
+            url: Some("http://www.gstatic.com/generate_204".to_string()),
+            interval: Some(300),
+            tolerance: None,
+            rules: vec![ProviderRule {
+                kind: RuleMatch::DomainKeyword,
+                value: "openai".to_string(),
+            }],
+            skipped_rules: 0,
+        };
+
+        let config = render_sing_box_config(
+            &cfg,
+            &Egress::Group(&group, vec![&node]),
+            &Chain::default(),
+            27890,
+            false,
+            CoreVersion::new(1, 11, 0),
+        )
+        .unwrap();
+
+        assert_eq!(config["outbounds"][0]["tag"], "proxy-1");
+        let urltest = &config["outbounds"][1];
+        assert_eq!(urltest["type"], "urltest");
+        assert_eq!(urltest["outbounds"][0], "proxy-1");
+        assert_eq!(urltest["interval"], "300s");
+        assert_eq!(config["route"]["rules"][1]["domain_keyword"][0], "openai");
+        assert_eq!(config["route"]["rules"][1]["outbound"], "proxy");
+    }
+
+    #[test]
+    fn dials_node_through_chain() {
+        let cfg = AppConfig::default();
+        let node = socks_node();
+        let hop: ProxyNode =
+            serde_yaml::from_str("{ name: hop, type: http, server: hop.example, port: 8080 }")
+                .unwrap();
+        let chain = Chain {
+            upstream: Some(socks_node()),
+            hops: vec![&hop],
+        };
+
+        let config = render_sing_box_config(
+            &cfg,
+            &Egress::Node(&node),
+            &chain,
+            27890,
+            true,
+            CoreVersion::new(1, 11, 0),
+        )
+        .unwrap();
+
+        let outbounds = &config["outbounds"];
+        assert_eq!(outbounds[0]["tag"], "proxy");
+        assert_eq!(outbounds[0]["detour"], "chain-2");
+        assert_eq!(outbounds[1]["tag"], "chain-1");
+        assert!(outbounds[1].get("detour").is_none());
+        assert_eq!(outbounds[2]["server"], "hop.example");
+        assert_eq!(outbounds[2]["detour"], "chain-1");
+    }
+
+    #[test]
+    fn renders_wireguard_as_endpoint_on_newer_cores() {
+        let cfg = AppConfig::default();
+        let node: ProxyNode = serde_yaml::from_str(
+            "{ name: wg, type: wireguard, server: wg.example, port: 51820, ip: 10.0.0.2, private-key: cHJpdg==, public-key: cHVi, reserved: [1, 2, 3], mtu: 1280 }",
+        )
+        .unwrap();
+        let render = |version| {
+            render_sing_box_config(
+                &cfg,
+                &Egress::Node(&node),
+                &Chain::default(),
+                27890,
+                true,
+                version,
+            )
+            .unwrap()
+        };
+
+        let legacy = render(CoreVersion::new(1, 10, 0));
+        assert_eq!(legacy["outbounds"][0]["type"], "wireguard");
+        assert_eq!(legacy["outbounds"][0]["local_address"][0], "10.0.0.2/32");
+        assert!(legacy.get("endpoints").is_none());
+
+        let current = render(CoreVersion::new(1, 11, 0));
+        assert_eq!(current["outbounds"][0]["type"], "direct");
+        let endpoint = &current["endpoints"][0];
+        assert_eq!(endpoint["tag"], "proxy");
+        assert_eq!(endpoint["mtu"], 1280);
+        assert_eq!(endpoint["peers"][0]["address"], "wg.example");
+        assert_eq!(endpoint["peers"][0]["reserved"][2], 3);
+    }
+
+    #[test]
+    fn renders_rule_sets_and_dns_for_the_core_schema() {
+        let mut cfg = AppConfig::default();
+        cfg.routing.rule_sets = vec!["https://rules.example/geosite-openai.srs".to_string()];
+        cfg.routing.dns = Some("tls://1.1.1.1".to_string());
+        let node = socks_node();
+        let render = |version| -> serde_json::Value {
+            let json = SingBox
+                .render_config(
+                    &cfg,
+                    &Egress::Node(&node),
+                    &Chain::default(),
+                    27890,
+                    false,
+                    version,
+                )
+                .unwrap();
+            serde_json::from_str(&json).unwrap()
+        };
+
+        let legacy = render(CoreVersion::new(1, 11, 0));
+        let rule_set = &legacy["route"]["rule_set"][0];
+        assert_eq!(rule_set["format"], "binary");
+        assert_eq!(rule_set["download_detour"], "proxy");
+        assert_eq!(legacy["route"]["rules"][1]["rule_set"][0], "rule-set-1");
+        assert_eq!(legacy["dns"]["servers"][0]["address"], "tls://1.1.1.1");
+        assert!(legacy["dns"]["servers"][0].get("type").is_none());
+
+        let current = render(CoreVersion::new(1, 12, 0));
+        let server = &current["dns"]["servers"][0];
+        assert_eq!(server["type"], "tls");
+        assert_eq!(server["server"], "1.1.1.1");
+        assert_eq!(server["detour"], "proxy");
+        assert!(server.get("address").is_none());
+    }
+
+    #[test]
+    fn resolves_named_dns_servers_locally() {
+        let legacy =
+            super::dns_config("https://dns.google/dns-query", CoreVersion::new(1, 10, 0)).unwrap();
+        assert_eq!(legacy["servers"][0]["address_resolver"], "local");
+        assert_eq!(legacy["servers"][1]["address"], "local");
+
+        let current =
+            super::dns_config("https://dns.google/resolve", CoreVersion::new(1, 12, 0)).unwrap();
+        assert_eq!(current["servers"][0]["domain_resolver"], "local");
+        assert_eq!(current["servers"][0]["path"], "/resolve");
+        assert_eq!(current["servers"][1]["type"], "local");
+
+        assert!(super::dns_config("ftp://1.1.1.1", CoreVersion::new(1, 12, 0)).is_err());
+    }
+
+    #[test]
+    fn builds_tls_for_https_proxies() {
+        let node: ProxyNode = serde_yaml::from_str(
+            "{ name: h, type: http, server: h.example, port: 443, tls: true, sni: cdn.example, skip-cert-verify: true, alpn: [h2], client-fingerprint: chrome }",
+        )
+        .unwrap();
+
+        let tls = &super::node_to_outbound(&node).unwrap()["tls"];
+        assert_eq!(tls["enabled"], true);
+        assert_eq!(tls["server_name"], "cdn.example");
+        assert_eq!(tls["insecure"], true);
+        assert_eq!(tls["alpn"][0], "h2");
+        assert_eq!(tls["utls"]["fingerprint"], "chrome");
+
+        let mut pinned = node.clone();
+        pinned.fingerprint = Some("ab".repeat(32));
+        assert!(super::node_to_outbound(&pinned).is_err());
+        assert!(!SingBox.supports(&pinned));
+    }
+
+    #[test]
+    fn maps_vmess_transports() {
+        let transport = |extra: &str| {
+            let node: ProxyNode = serde_yaml::from_str(&format!(
+                "{{ name: v, type: vmess, server: v.example, port: 443, uuid: u-1, {extra} }}"
+            ))
+            .unwrap();
+            super::node_to_outbound(&node).map(|o| o["transport"].clone())
+        };
+
+        let ws = transport(
+            "network: ws, ws-opts: { path: /ws, max-early-data: 2048, early-data-header-name: Sec-WebSocket-Protocol }",
+        )
+        .unwrap();
+        assert_eq!(ws["type"], "ws");
+        assert_eq!(ws["max_early_data"], 2048);
+        assert_eq!(ws["early_data_header_name"], "Sec-WebSocket-Protocol");
+
+        let upgrade = transport(
+            "network: ws, ws-opts: { path: /up, headers: { Host: cdn.example }, v2ray-http-upgrade: true }",
+        )
+        .unwrap();
+        assert_eq!(upgrade["type"], "httpupgrade");
+        assert_eq!(upgrade["host"], "cdn.example");
+        assert!(upgrade.get("headers").is_none());
+
+        let h2 =
+            transport("tls: true, network: h2, h2-opts: { host: [h.example], path: /h2 }").unwrap();
+        assert_eq!(h2["type"], "http");
+        assert_eq!(h2["host"][0], "h.example");
+
+        assert!(transport("network: h2").is_err(), "h2 without TLS");
+        assert_eq!(
+            transport("network: http").unwrap_err(),
+            Unsupported::Transport("http".to_string()),
+            "TCP header obfuscation"
+        );
+        assert!(transport("network: tcp").unwrap().is_null());
+    }
+
+    #[test]
+    fn maps_ssh_key_path_and_host_keys() {
+        let node: ProxyNode = serde_yaml::from_str(
+            "{ name: bastion, type: ssh, server: b.example, port: 22, username: ops, private-key: ~/.ssh/id_ed25519, host-key: ['ssh-ed25519 AAAA'] }",
+        )
+        .unwrap();
+
+        let outbound = super::node_to_outbound(&node).unwrap();
+        assert_eq!(outbound["user"], "ops");
+        assert_eq!(outbound["private_key_path"], "~/.ssh/id_ed25519");
+        assert!(outbound.get("private_key").is_none());
+        assert_eq!(outbound["host_key"][0], "ssh-ed25519 AAAA");
+    }
+}
@@ -1,16 +1,33 @@
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
-use crate::config::AppPaths;
+use crate::config::{
+    AppConfig, AppPaths, SubscriptionAlertConfig, SubscriptionConfig, write_private,
+};
+use crate::install::sha256_hex;
+use crate::secrets::redact_url;
+
+const USERINFO_HEADER: &str = "subscription-userinfo";
+const SECONDS_PER_DAY: u64 = 86_400;
+/// `ProxyNode::source` of `[[nodes]]` and `nodes.yaml` nodes.
+const LOCAL_SOURCE: &str = "local";
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ClashSubscription {
     pub proxies: Option<Vec<ProxyNode>>,
+    /// Kept as raw YAML so one odd group does not reject the subscription.
+    #[serde(default, rename = "proxy-groups")]
+    pub proxy_groups: Vec<serde_yaml::Value>,
+    #[serde(default)]
+    pub rules: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ProxyNode {
     pub name: String,
     #[serde(rename = "type")]
@@ -27,17 +44,47 @@ pub struct ProxyNode {
     pub network: Option<String>,
     pub servername: Option<String>,
     pub sni: Option<String>,
+    #[serde(rename = "skip-cert-verify")]
+    pub skip_cert_verify: Option<bool>,
+    pub alpn: Option<Vec<String>>,
+    /// uTLS client hello to imitate, e.g. `chrome`.
+    #[serde(rename = "client-fingerprint")]
+    pub client_fingerprint: Option<String>,
+    /// SHA-256 of the server certificate to pin.
+    pub fingerprint: Option<String>,
     #[serde(rename = "ws-opts")]
     pub ws_opts: Option<WsOpts>,
     #[serde(rename = "grpc-opts")]
     pub grpc_opts: Option<GrpcOpts>,
+    #[serde(rename = "h2-opts")]
+    pub h2_opts: Option<H2Opts>,
     pub plugin: Option<String>,
+    /// Remaining Clash fields, kept for cores that read Clash nodes natively.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml::Value>,
+    /// Name of the subscription this node was loaded from.
+    #[serde(skip)]
+    pub source: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WsOpts {
     pub path: Option<String>,
-    pub headers: Option<std::collections::BTreeMap<String, String>>,
+    pub headers: Option<BTreeMap<String, String>>,
+    /// Bytes of the first payload sent with the handshake.
+    #[serde(rename = "max-early-data")]
+    pub max_early_data: Option<u32>,
+    #[serde(rename = "early-data-header-name")]
+    pub early_data_header_name: Option<String>,
+    /// Plain HTTP upgrade instead of a WebSocket handshake.
+    #[serde(rename = "v2ray-http-upgrade")]
+    pub v2ray_http_upgrade: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct H2Opts {
+    pub host: Option<Vec<String>>,
+    pub path: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -47,54 +94,417 @@ pub struct GrpcOpts {
 }
 
 impl ProxyNode {
-    pub fn is_supported_for_sing_box(&self) -> bool {
-        let base = self.server.is_some() && self.port.is_some();
-        if !base {
-            return false;
+    /// A string field without a typed counterpart, e.g. `private-key`.
+    pub fn extra_str(&self, key: &str) -> Option<&str> {
+        self.extra.get(key).and_then(|v| v.as_str())
+    }
+
+    /// Identity of the endpoint regardless of its display name, used to
+    /// dedup subscriptions and to keep `runtime.selected_node` across
+    /// provider renames: `type|server|port|` followed by a hash of the
+    /// credentials and transport, so credentials are never stored in clear.
+    pub fn fingerprint(&self) -> String {
+        let credentials = [
+            self.username.as_deref(),
+            self.password.as_deref(),
+            self.uuid.as_deref(),
+            self.extra_str("private-key"),
+            self.network.as_deref(),
+        ]
+        .map(Option::unwrap_or_default)
+        .join("\n");
+        format!(
+            "{}|{}|{}|{}",
+            self.node_type,
+            self.server.as_deref().unwrap_or_default(),
+            self.port.unwrap_or_default(),
+            &sha256_hex(credentials.as_bytes())[..16]
+        )
+    }
+}
+
+/// Traffic and expiry figures from the `subscription-userinfo` response header.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SubscriptionUserInfo {
+    pub upload: Option<u64>,
+    pub download: Option<u64>,
+    pub total: Option<u64>,
+    /// Unix timestamp in seconds.
+    pub expire: Option<u64>,
+}
+
+/// Download metadata cached next to each subscription YAML.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SubscriptionMeta {
+    pub fetched_at: u64,
+    pub userinfo: Option<SubscriptionUserInfo>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// `Content-Type` returned with the cached body.
+    pub content_type: Option<String>,
+}
+
+impl SubscriptionMeta {
+    pub fn age_secs(&self, now: u64) -> u64 {
+        now.saturating_sub(self.fetched_at)
+    }
+
+    pub fn is_stale(&self, max_age_hours: u64, now: u64) -> bool {
+        max_age_hours > 0 && self.age_secs(now) > max_age_hours.saturating_mul(3_600)
+    }
+}
+
+pub enum DownloadOutcome {
+    Updated(Vec<ProxyNode>),
+    /// The server answered `304 Not Modified`; the cache was kept.
+    NotModified,
+}
+
+impl SubscriptionUserInfo {
+    /// Parses `upload=…; download=…; total=…; expire=…`. Unknown keys and
+    /// malformed values are ignored.
+    pub fn parse(header: &str) -> Self {
+        let mut info = Self::default();
+        for pair in header.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<f64>() else {
+                continue;
+            };
+            if !value.is_finite() || value < 0.0 {
+                continue;
+            }
+            let value = Some(value as u64);
+            match key.trim().to_ascii_lowercase().as_str() {
+                "upload" => info.upload = value,
+                "download" => info.download = value,
+                "total" => info.total = value,
+                "expire" => info.expire = value.filter(|&v| v > 0),
+                _ => {}
+            }
+        }
+        info
+    }
+
+    pub fn used(&self) -> u64 {
+        // Saturating: the header comes from the provider and may be bogus.
+        self.upload
+            .unwrap_or(0)
+            .saturating_add(self.download.unwrap_or(0))
+    }
+
+    pub fn remaining(&self) -> Option<u64> {
+        self.total
+            .filter(|&t| t > 0)
+            .map(|t| t.saturating_sub(self.used()))
+    }
+
+    pub fn summary(&self, now: u64) -> String {
+        let mut parts = Vec::new();
+        match self.total.filter(|&t| t > 0) {
+            Some(total) => parts.push(format!(
+                "used {} of {} ({} left)",
+                format_bytes(self.used()),
+                format_bytes(total),
+                format_bytes(self.remaining().unwrap_or(0))
+            )),
+            None => parts.push(format!("used {}", format_bytes(self.used()))),
         }
-        match self.node_type.as_str() {
-            "socks5" | "socks" | "http" => true,
-            "ss" => self.password.is_some() && self.cipher.is_some() && self.plugin.is_none(),
-            "vmess" => self.uuid.is_some(),
-            _ => false,
+        match self.expire {
+            Some(expire) if expire > now => parts.push(format!(
+                "expires {} ({} days left)",
+                format_unix_date(expire),
+                (expire - now) / SECONDS_PER_DAY
+            )),
+            Some(expire) => parts.push(format!("expired {}", format_unix_date(expire))),
+            None => parts.push("no expiry".to_string()),
         }
+        parts.join(", ")
+    }
+
+    /// Returns human-readable warnings for low quota or close expiry.
+    pub fn warnings(&self, alerts: &SubscriptionAlertConfig, now: u64) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let (Some(total), Some(remaining)) = (self.total, self.remaining()) {
+            let percent = remaining as f64 * 100.0 / total as f64;
+            if percent < f64::from(alerts.min_remaining_percent) {
+                warnings.push(format!(
+                    "only {} ({percent:.1}%) of traffic quota left",
+                    format_bytes(remaining)
+                ));
+            }
+        }
+        if let Some(expire) = self.expire {
+            if expire <= now {
+                warnings.push(format!("expired on {}", format_unix_date(expire)));
+            } else if expire - now <= alerts.expiry_warn_days.saturating_mul(SECONDS_PER_DAY) {
+                warnings.push(format!(
+                    "expires on {} (within {} days)",
+                    format_unix_date(expire),
+                    alerts.expiry_warn_days
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
+/// Formats a Unix timestamp as a UTC `YYYY-MM-DD` date.
+fn format_unix_date(secs: u64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar).
+    let days = (secs / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+pub fn read_subscription_meta(paths: &AppPaths, name: &str) -> Result<Option<SubscriptionMeta>> {
+    let path = paths.subscription_meta(name);
+    if !path.exists() {
+        return Ok(None);
     }
+    let raw =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let meta = serde_json::from_str(&raw)
+        .with_context(|| format!("Invalid subscription metadata {}", path.display()))?;
+    Ok(Some(meta))
+}
+
+fn write_subscription_meta(paths: &AppPaths, name: &str, meta: &SubscriptionMeta) -> Result<()> {
+    let path = paths.subscription_meta(name);
+    let content =
+        serde_json::to_string_pretty(meta).context("Failed to serialize subscription metadata")?;
+    write_private(&path, content)
 }
 
-pub async fn download_subscription(url: &str, paths: &AppPaths) -> Result<String> {
-    let client = reqwest::Client::new();
-    let response = client
+/// Downloads a subscription into its cache. Unless `force` is set, the
+/// request is conditional on the cached `ETag`/`Last-Modified` validators.
+/// The cache is only replaced when the new body parses, and the replaced
+/// cache is kept as `<NAME>.yaml.bak`. With `dry_run` nothing is written.
+pub async fn download_subscription(
+    client: &reqwest::Client,
+    sub: &SubscriptionConfig,
+    paths: &AppPaths,
+    force: bool,
+    dry_run: bool,
+) -> Result<DownloadOutcome> {
+    let Some(url) = sub.url.as_deref() else {
+        bail!(no_source_message(sub));
+    };
+    let cache = paths.subscription_cache(&sub.name);
+    let previous = if cache.exists() {
+        read_subscription_meta(paths, &sub.name).ok().flatten()
+    } else {
+        None
+    };
+
+    let mut request = client
         .get(url)
+        .header(reqwest::header::USER_AGENT, sub.effective_user_agent());
+    for (name, value) in &sub.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    if let Some(meta) = previous.as_ref().filter(|_| !force) {
+        if let Some(etag) = &meta.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &meta.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request
         .send()
         .await
-        .with_context(|| format!("Failed to download subscription from {url}"))?;
+        .map_err(reqwest::Error::without_url)
+        .with_context(|| {
+            format!(
+                "Failed to download subscription '{}' from {}",
+                sub.name,
+                redact_url(url)
+            )
+        })?;
     let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let userinfo = header(USERINFO_HEADER).map(|v| SubscriptionUserInfo::parse(&v));
+    let etag = header(reqwest::header::ETAG.as_str());
+    let last_modified = header(reqwest::header::LAST_MODIFIED.as_str());
+    let content_type = header(reqwest::header::CONTENT_TYPE.as_str());
+
+    if status == reqwest::StatusCode::NOT_MODIFIED
+        && let Some(previous) = previous
+    {
+        if dry_run {
+            return Ok(DownloadOutcome::NotModified);
+        }
+        write_subscription_meta(
+            paths,
+            &sub.name,
+            &SubscriptionMeta {
+                fetched_at: unix_now(),
+                userinfo: userinfo.or(previous.userinfo),
+                etag: etag.or(previous.etag),
+                last_modified: last_modified.or(previous.last_modified),
+                content_type: previous.content_type,
+            },
+        )?;
+        return Ok(DownloadOutcome::NotModified);
+    }
     if !status.is_success() {
         bail!("Subscription request failed with status {status}");
     }
     let body = response
         .text()
         .await
+        .map_err(reqwest::Error::without_url)
         .context("Failed to decode subscription response as text")?;
-    paths.ensure_dirs()?;
-    fs::write(&paths.subscription_yaml, &body).with_context(|| {
+    let nodes = parse_subscription(&body).with_context(|| {
         format!(
-            "Failed to write subscription cache {}",
-            paths.subscription_yaml.display()
+            "Downloaded subscription is invalid (content type: {}, User-Agent: {}); keeping the previous cache",
+            content_type.as_deref().unwrap_or("unknown"),
+            sub.effective_user_agent()
         )
     })?;
-    Ok(body)
+    if !dry_run {
+        store_subscription(
+            paths,
+            &sub.name,
+            &body,
+            &SubscriptionMeta {
+                fetched_at: unix_now(),
+                userinfo,
+                etag,
+                last_modified,
+                content_type,
+            },
+        )?;
+    }
+    Ok(DownloadOutcome::Updated(nodes))
+}
+
+/// Caches a subscription body read from a file or stdin, the same way a
+/// download is cached.
+pub fn import_subscription(
+    paths: &AppPaths,
+    sub: &SubscriptionConfig,
+    body: &str,
+    dry_run: bool,
+) -> Result<DownloadOutcome> {
+    let nodes = parse_subscription(body)
+        .context("Imported subscription is invalid; keeping the previous cache")?;
+    if !dry_run {
+        let meta = SubscriptionMeta {
+            fetched_at: unix_now(),
+            ..SubscriptionMeta::default()
+        };
+        store_subscription(paths, &sub.name, body, &meta)?;
+    }
+    Ok(DownloadOutcome::Updated(nodes))
+}
+
+/// Re-reads a `file://` subscription; works offline.
+pub fn read_local_subscription(
+    paths: &AppPaths,
+    sub: &SubscriptionConfig,
+    dry_run: bool,
+) -> Result<DownloadOutcome> {
+    let Some(path) = sub.file_path() else {
+        bail!(no_source_message(sub));
+    };
+    let body = read_subscription_input(&path.to_string_lossy())?;
+    import_subscription(paths, sub, &body, dry_run)
+}
+
+/// Reads a subscription from a path, or from stdin for `-`.
+pub fn read_subscription_input(source: &str) -> Result<String> {
+    if source == "-" {
+        let mut body = String::new();
+        std::io::stdin()
+            .read_to_string(&mut body)
+            .context("Failed to read subscription from stdin")?;
+        return Ok(body);
+    }
+    fs::read_to_string(source).with_context(|| format!("Failed to read subscription file {source}"))
+}
+
+fn no_source_message(sub: &SubscriptionConfig) -> String {
+    format!(
+        "Subscription '{0}' has no URL; import it with `route-cli update {0} --from <FILE|->`",
+        sub.name
+    )
+}
+
+/// Replaces the cache, keeping the previous one as `<NAME>.yaml.bak`.
+fn store_subscription(
+    paths: &AppPaths,
+    name: &str,
+    body: &str,
+    meta: &SubscriptionMeta,
+) -> Result<()> {
+    paths.ensure_dirs()?;
+    let cache = paths.subscription_cache(name);
+    if cache.exists() {
+        let previous =
+            fs::read(&cache).with_context(|| format!("Failed to back up {}", cache.display()))?;
+        write_private(&paths.subscription_backup(name), previous)?;
+    }
+    write_private(&cache, body)?;
+    write_subscription_meta(paths, name, meta)
 }
 
-pub fn read_cached_subscription(paths: &AppPaths) -> Result<String> {
-    if !paths.subscription_yaml.exists() {
+/// Whether a cached subscription is older than `max_age_hours`. Caches without
+/// metadata count as stale.
+pub fn is_cache_stale(paths: &AppPaths, name: &str, max_age_hours: u64, now: u64) -> bool {
+    match read_subscription_meta(paths, name) {
+        Ok(Some(meta)) => meta.is_stale(max_age_hours, now),
+        _ => max_age_hours > 0,
+    }
+}
+
+pub fn read_cached_subscription(paths: &AppPaths, name: &str) -> Result<String> {
+    let cache = paths.subscription_cache(name);
+    if !cache.exists() {
         bail!(
-            "Subscription cache not found at {}. Run `route-cli update` first.",
-            paths.subscription_yaml.display()
+            "Subscription cache for '{name}' not found at {}. Run `route-cli update` first.",
+            cache.display()
         );
     }
-    let text = fs::read_to_string(&paths.subscription_yaml)
-        .with_context(|| format!("Failed to read {}", paths.subscription_yaml.display()))?;
+    let text = fs::read_to_string(&cache)
+        .with_context(|| format!("Failed to read {}", cache.display()))?;
     Ok(text)
 }
 
@@ -107,3 +517,286 @@ pub fn parse_subscription(raw: &str) -> Result<Vec<ProxyNode>> {
     }
     Ok(proxies)
 }
+
+/// User-defined nodes: `[[nodes]]` from config.toml, then `nodes.yaml`.
+pub fn load_local_nodes(cfg: &AppConfig, paths: &AppPaths) -> Result<Vec<ProxyNode>> {
+    let mut nodes = cfg.nodes.clone();
+    if paths.nodes_yaml.exists() {
+        let raw = fs::read_to_string(&paths.nodes_yaml)
+            .with_context(|| format!("Failed to read {}", paths.nodes_yaml.display()))?;
+        let parsed: ClashSubscription = serde_yaml::from_str(&raw)
+            .with_context(|| format!("Invalid node file {}", paths.nodes_yaml.display()))?;
+        nodes.extend(parsed.proxies.unwrap_or_default());
+    }
+    for node in &mut nodes {
+        node.source = LOCAL_SOURCE.to_string();
+    }
+    Ok(nodes)
+}
+
+/// Loads local nodes and every cached subscription into one node pool.
+pub fn load_node_pool(cfg: &AppConfig, paths: &AppPaths) -> Result<Vec<ProxyNode>> {
+    let local = load_local_nodes(cfg, paths)?;
+    if cfg.subscriptions.is_empty() && local.is_empty() {
+        bail!(
+            "No subscription or local node configured. Run `route-cli login-sub --url <URL>` or `route-cli add-node`"
+        );
+    }
+    let mut sources = Vec::new();
+    let mut skipped = Vec::new();
+    for sub in &cfg.subscriptions {
+        if !paths.subscription_cache(&sub.name).exists() {
+            continue;
+        }
+        // One unreadable cache should not take the other sources down.
+        match read_cached_subscription(paths, &sub.name)
+            .and_then(|raw| parse_subscription(&raw))
+            .with_context(|| format!("Invalid cache for subscription '{}'", sub.name))
+        {
+            Ok(nodes) => sources.push((sub.name.clone(), nodes)),
+            Err(err) => skipped.push((sub.name.as_str(), err)),
+        }
+    }
+    if sources.is_empty() && local.is_empty() {
+        if let Some((name, err)) = skipped.pop() {
+            return Err(err.context(format!(
+                "No usable subscription cache. Run `route-cli update {name} --force`."
+            )));
+        }
+        bail!("No subscription cache found. Run `route-cli update` first.");
+    }
+    for (name, err) in &skipped {
+        // stderr keeps `--output json` listings parseable.
+        eprintln!("[WARN] {err:#}; skipped, run `route-cli update {name} --force`");
+    }
+    let prefix_names = prefixes_source_names(cfg, !local.is_empty());
+    Ok(with_local_nodes(
+        local,
+        merge_sources(sources, prefix_names),
+    ))
+}
+
+/// Whether subscription node names get a `<source>/` prefix: whenever the
+/// pool merges more than one source, local nodes included, so names from
+/// different sources cannot clash.
+pub fn prefixes_source_names(cfg: &AppConfig, has_local_nodes: bool) -> bool {
+    cfg.subscriptions.len() + usize::from(has_local_nodes) > 1
+}
+
+/// Whether `node` comes from `[[nodes]]` or `nodes.yaml`.
+pub fn is_local_node(node: &ProxyNode) -> bool {
+    node.source == LOCAL_SOURCE
+}
+
+/// Puts local nodes first under their own names; subscription nodes for the
+/// same endpoint are dropped.
+fn with_local_nodes(mut local: Vec<ProxyNode>, subscribed: Vec<ProxyNode>) -> Vec<ProxyNode> {
+    let keys: HashSet<String> = local.iter().map(ProxyNode::fingerprint).collect();
+    local.extend(
+        subscribed
+            .into_iter()
+            .filter(|n| !keys.contains(&n.fingerprint())),
+    );
+    local
+}
+
+/// Merges nodes from several subscriptions in priority order. Nodes pointing
+/// at an endpoint already seen are dropped; with `prefix_names` each node name
+/// is prefixed by its source so names stay unique across providers.
+fn merge_sources(sources: Vec<(String, Vec<ProxyNode>)>, prefix_names: bool) -> Vec<ProxyNode> {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    for (source, nodes) in sources {
+        for mut node in nodes {
+            if !seen.insert(node.fingerprint()) {
+                continue;
+            }
+            if prefix_names {
+                node.name = format!("{source}/{}", node.name);
+            }
+            node.source = source.clone();
+            merged.push(node);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SubscriptionMeta, SubscriptionUserInfo, format_unix_date, load_node_pool, merge_sources,
+        parse_subscription, with_local_nodes,
+    };
+    use crate::config::{AppConfig, AppPaths, SubscriptionAlertConfig, SubscriptionConfig};
+    use crate::test_util::make_temp_dir;
+
+    const SUB_A: &str = r#"
+proxies:
+  - { name: "SG 01", type: socks5, server: sg.example.com, port: 1080 }
+  - { name: "US 01", type: http, server: us.example.com, port: 8080 }
+"#;
+    const SUB_B: &str = r#"
+proxies:
+  - { name: "Singapore", type: socks5, server: sg.example.com, port: 1080 }
+  - { name: "KR 01", type: socks5, server: kr.example.com, port: 1080 }
+"#;
+
+    #[test]
+    fn merges_sources_with_prefix_and_dedup() {
+        let sources = vec![
+            (
+                "main".to_string(),
+                parse_subscription(SUB_A).expect("valid yaml"),
+            ),
+            (
+                "backup".to_string(),
+                parse_subscription(SUB_B).expect("valid yaml"),
+            ),
+        ];
+
+        let merged = merge_sources(sources, true);
+
+        let names: Vec<&str> = merged.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["main/SG 01", "main/US 01", "backup/KR 01"]);
+        assert_eq!(merged[2].source, "backup");
+    }
+
+    #[test]
+    fn keeps_nodes_that_differ_only_in_transport() {
+        let tcp = parse_subscription(
+            "proxies: [{ name: A, type: vmess, server: v.example, port: 443, uuid: u1 }]",
+        )
+        .expect("valid yaml");
+        let ws = parse_subscription(
+            "proxies: [{ name: B, type: vmess, server: v.example, port: 443, uuid: u1, network: ws }]",
+        )
+        .expect("valid yaml");
+        assert_ne!(tcp[0].fingerprint(), ws[0].fingerprint());
+
+        let merged = merge_sources(vec![("a".to_string(), tcp), ("b".to_string(), ws)], true);
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn skips_unreadable_caches() {
+        let paths = AppPaths::in_root(&make_temp_dir("route-cli-broken-cache"));
+        paths.ensure_dirs().unwrap();
+        let mut cfg = AppConfig::default();
+        for (name, body) in [("main", SUB_A), ("broken", "proxies: [")] {
+            cfg.upsert_subscription(SubscriptionConfig::new(name, None));
+            std::fs::write(paths.subscription_cache(name), body).unwrap();
+        }
+
+        let pool = load_node_pool(&cfg, &paths).expect("main should still load");
+        assert_eq!(pool.len(), 2);
+        assert!(pool.iter().all(|n| n.source == "main"));
+
+        cfg.remove_subscription("main");
+        let err = load_node_pool(&cfg, &paths).unwrap_err();
+        assert!(format!("{err:#}").contains("'broken'"), "{err:#}");
+    }
+
+    #[test]
+    fn local_nodes_take_precedence() {
+        let local = parse_subscription(SUB_B).expect("valid yaml");
+        let subscribed = merge_sources(
+            vec![(
+                "main".to_string(),
+                parse_subscription(SUB_A).expect("valid yaml"),
+            )],
+            false,
+        );
+
+        let pool = with_local_nodes(local, subscribed);
+        let names: Vec<&str> = pool.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["Singapore", "KR 01", "US 01"]);
+    }
+
+    #[test]
+    fn prefixes_a_single_subscription_next_to_local_nodes() {
+        let paths = AppPaths::in_root(&make_temp_dir("route-cli-local-and-sub"));
+        paths.ensure_dirs().unwrap();
+        let mut cfg = AppConfig::default();
+        cfg.upsert_subscription(SubscriptionConfig::new("main", None));
+        std::fs::write(paths.subscription_cache("main"), SUB_A).unwrap();
+        cfg.nodes = parse_subscription(
+            "proxies: [{ name: SG 01, type: socks5, server: home.example, port: 1080 }]",
+        )
+        .unwrap();
+
+        let pool = load_node_pool(&cfg, &paths).unwrap();
+        let names: Vec<&str> = pool.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["SG 01", "main/SG 01", "main/US 01"]);
+
+        cfg.nodes.clear();
+        let pool = load_node_pool(&cfg, &paths).unwrap();
+        assert_eq!(pool[0].name, "SG 01");
+    }
+
+    #[test]
+    fn keeps_plain_names_for_single_source() {
+        let sources = vec![(
+            "default".to_string(),
+            parse_subscription(SUB_A).expect("valid yaml"),
+        )];
+
+        let merged = merge_sources(sources, false);
+
+        assert_eq!(merged[0].name, "SG 01");
+        assert_eq!(merged[0].source, "default");
+    }
+
+    #[test]
+    fn parses_userinfo_header() {
+        let info = SubscriptionUserInfo::parse(
+            "upload=1073741824; download=2147483648; total=10737418240; expire=1767225600",
+        );
+
+        assert_eq!(info.used(), 3 * 1024 * 1024 * 1024);
+        assert_eq!(info.remaining(), Some(7 * 1024 * 1024 * 1024));
+        assert_eq!(info.expire, Some(1_767_225_600));
+        assert_eq!(format_unix_date(1_767_225_600), "2026-01-01");
+    }
+
+    #[test]
+    fn saturates_on_out_of_range_values() {
+        let info = SubscriptionUserInfo::parse(
+            "upload=18446744073709551615; download=1; total=18446744073709551615; expire=18446744073709551615",
+        );
+        assert_eq!(info.used(), u64::MAX);
+        assert_eq!(info.remaining(), Some(0));
+        assert!(!info.summary(0).is_empty());
+
+        let alerts = SubscriptionAlertConfig {
+            min_remaining_percent: 0,
+            expiry_warn_days: u64::MAX,
+        };
+        assert_eq!(info.warnings(&alerts, 0).len(), 1);
+    }
+
+    #[test]
+    fn warns_on_low_quota_and_close_expiry() {
+        let info = SubscriptionUserInfo::parse("upload=0; download=95; total=100; expire=864000");
+        let alerts = SubscriptionAlertConfig {
+            min_remaining_percent: 10,
+            expiry_warn_days: 7,
+        };
+
+        assert_eq!(info.warnings(&alerts, 0).len(), 1);
+        assert_eq!(info.warnings(&alerts, 4 * 86_400).len(), 2);
+        assert!(info.warnings(&alerts, 864_000)[1].starts_with("expired"));
+    }
+
+    #[test]
+    fn cache_staleness_respects_max_age() {
+        let meta = SubscriptionMeta {
+            fetched_at: 1_000,
+            ..SubscriptionMeta::default()
+        };
+
+        assert!(!meta.is_stale(1, 1_000 + 3_600));
+        assert!(meta.is_stale(1, 1_000 + 3_601));
+        assert!(!meta.is_stale(0, u64::MAX));
+        assert!(!meta.is_stale(u64::MAX, u64::MAX));
+    }
+}
@@ -0,0 +1,10 @@
+/// Fresh directory under the system temp dir, unique per process and call.
+pub fn make_temp_dir(prefix: &str) -> std::path::PathBuf {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("time should be valid")
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("{prefix}-{}-{stamp}", std::process::id()));
+    std::fs::create_dir_all(&path).expect("temp dir should be creatable");
+    path
+}
@@ -0,0 +1,356 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value, json};
+
+use crate::backend::{
+    CoreBackend, CoreVersion, Egress, Unsupported, host_labels, reject_sing_box_routing, required,
+};
+use crate::chain::Chain;
+use crate::config::AppConfig;
+use crate::subscription::ProxyNode;
+
+const BINARY: &str = if cfg!(windows) { "xray.exe" } else { "xray" };
+
+pub struct Xray;
+
+impl CoreBackend for Xray {
+    fn name(&self) -> &'static str {
+        "xray"
+    }
+
+    fn binary_name(&self) -> &'static str {
+        BINARY
+    }
+
+    fn config_extension(&self) -> &'static str {
+        "json"
+    }
+
+    /// The socks inbound accepts HTTP proxy requests from 1.8.24, which the
+    /// single local port relies on.
+    fn minimum_version(&self) -> CoreVersion {
+        CoreVersion::new(1, 8, 24)
+    }
+
+    fn check(&self, node: &ProxyNode) -> Result<(), Unsupported> {
+        node_to_outbound(node).map(|_| ())
+    }
+
+    fn render_config(
+        &self,
+        cfg: &AppConfig,
+        egress: &Egress,
+        chain: &Chain,
+        listen_port: u16,
+        route_all: bool,
+        _version: CoreVersion,
+    ) -> Result<String> {
+        reject_sing_box_routing(cfg, self.name())?;
+        let node = match egress {
+            Egress::Node(node) => node,
+            Egress::Group(group, _) => bail!(
+                "xray cannot run provider group '{}'; pick a node with `route-cli use-node`",
+                group.name
+            ),
+        };
+        let mut outbound = node_to_outbound(node)
+            .with_context(|| format!("Selected node '{}' is unsupported by xray", node.name))?;
+        let direct = json!({ "protocol": "freedom", "tag": "direct" });
+
+        // Each chain link is dialed through the previous one via `dialerProxy`.
+        let mut chain_outbounds: Vec<Value> = Vec::new();
+        for (i, link) in chain.links().into_iter().enumerate() {
+            let mut hop = node_to_outbound(link)
+                .with_context(|| format!("Chain hop '{}' is unsupported by xray", link.name))?;
+            hop["tag"] = json!(format!("chain-{}", i + 1));
+            if let Some(previous) = chain_outbounds.last() {
+                hop["streamSettings"]["sockopt"]["dialerProxy"] = previous["tag"].clone();
+            }
+            chain_outbounds.push(hop);
+        }
+        if let Some(last) = chain_outbounds.last() {
+            outbound["streamSettings"]["sockopt"]["dialerProxy"] = last["tag"].clone();
+        }
+
+        // Xray sends unmatched traffic to the first outbound.
+        let (mut outbounds, rules) = if route_all {
+            (vec![outbound, direct], vec![])
+        } else {
+            let domains: Vec<String> = cfg
+                .routing
+                .proxy_domains
+                .iter()
+                .map(|d| format!("domain:{d}"))
+                .collect();
+            let rules = if domains.is_empty() {
+                vec![]
+            } else {
+                vec![json!({ "type": "field", "domain": domains, "outboundTag": "proxy" })]
+            };
+            (vec![direct, outbound], rules)
+        };
+        outbounds.extend(chain_outbounds);
+
+        let config = json!({
+            "log": { "loglevel": "warning" },
+            "inbounds": [{
+                "tag": "mixed-in",
+                "listen": "127.0.0.1",
+                "port": listen_port,
+                "protocol": "socks",
+                "settings": { "udp": true },
+                "sniffing": { "enabled": true, "destOverride": ["http", "tls"] }
+            }],
+            "outbounds": outbounds,
+            "routing": { "domainStrategy": "AsIs", "rules": rules }
+        });
+        serde_json::to_string_pretty(&config).context("Failed to serialize xray config")
+    }
+
+    fn run_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec!["run".into(), "-c".into(), config_path.into()]
+    }
+
+    fn check_args(&self, config_path: &Path) -> Vec<OsString> {
+        vec![
+            "run".into(),
+            "-test".into(),
+            "-c".into(),
+            config_path.into(),
+        ]
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        &["version"]
+    }
+
+    fn release_repo(&self) -> &'static str {
+        "XTLS/Xray-core"
+    }
+
+    fn host_asset_name(&self, _version: &str) -> Result<String> {
+        let (os, arch) = host_labels(
+            self.name(),
+            ["windows", "linux", "macos"],
+            ["64", "arm64-v8a", "32"],
+        )?;
+        Ok(format!("Xray-{os}-{arch}.zip"))
+    }
+}
+
+fn node_to_outbound(node: &ProxyNode) -> Result<Value, Unsupported> {
+    let server = required(node.server.as_ref(), "server")?;
+    let port = required(node.port, "port")?;
+    // Clash pins the certificate hash, Xray the whole chain; refuse rather
+    // than drop the pin.
+    if node.fingerprint.is_some() {
+        return Err(Unsupported::Feature("certificate pinning (`fingerprint`)"));
+    }
+    let users = |user: &Option<String>, pass: &Option<String>| match (user, pass) {
+        (Some(user), Some(pass)) => json!([{ "user": user, "pass": pass }]),
+        _ => json!([]),
+    };
+    let (protocol, settings) = match node.node_type.as_str() {
+        "socks5" | "socks" => (
+            "socks",
+            json!({ "servers": [{
+                "address": server,
+                "port": port,
+                "users": users(&node.username, &node.password)
+            }] }),
+        ),
+        "http" => (
+            "http",
+            json!({ "servers": [{
+                "address": server,
+                "port": port,
+                "users": users(&node.username, &node.password)
+            }] }),
+        ),
+        "ss" => {
+            if let Some(plugin) = &node.plugin {
+                return Err(Unsupported::Plugin(plugin.clone()));
+            }
+            (
+                "shadowsocks",
+                json!({ "servers": [{
+                    "address": server,
+                    "port": port,
+                    "method": required(node.cipher.as_ref(), "cipher")?,
+                    "password": required(node.password.as_ref(), "password")?
+                }] }),
+            )
+        }
+        "vmess" => (
+            "vmess",
+            json!({ "vnext": [{
+                "address": server,
+                "port": port,
+                "users": [{
+                    "id": required(node.uuid.as_ref(), "uuid")?,
+                    "alterId": node.alter_id.unwrap_or(0),
+                    "security": node.cipher.as_deref().unwrap_or("auto")
+                }]
+            }] }),
+        ),
+        "vless" => {
+            // REALITY needs keys Xray would have to be told about explicitly.
+            if node.extra.contains_key("reality-opts") {
+                return Err(Unsupported::Feature("REALITY"));
+            }
+            let uuid = required(node.uuid.as_ref(), "uuid")?;
+            let mut user = json!({ "id": uuid, "encryption": "none" });
+            if let Some(flow) = node.extra.get("flow").and_then(|f| f.as_str()) {
+                user["flow"] = json!(flow);
+            }
+            (
+                "vless",
+                json!({ "vnext": [{ "address": server, "port": port, "users": [user] }] }),
+            )
+        }
+        "trojan" => (
+            "trojan",
+            json!({ "servers": [{
+                "address": server,
+                "port": port,
+                "password": required(node.password.as_ref(), "password")?
+            }] }),
+        ),
+        other => return Err(Unsupported::Type(other.to_string())),
+    };
+
+    let mut outbound = json!({ "protocol": protocol, "tag": "proxy", "settings": settings });
+    if matches!(protocol, "vmess" | "vless" | "trojan") || node.tls.unwrap_or(false) {
+        outbound["streamSettings"] = stream_settings(node)?;
+    }
+    Ok(outbound)
+}
+
+fn stream_settings(node: &ProxyNode) -> Result<Value, Unsupported> {
+    let ws_opts = node.ws_opts.as_ref();
+    let mut network = node.network.as_deref().unwrap_or("tcp");
+    if network == "ws" && ws_opts.and_then(|w| w.v2ray_http_upgrade) == Some(true) {
+        network = "httpupgrade";
+    }
+    let mut stream = Map::<String, Value>::new();
+    stream.insert("network".to_string(), json!(network));
+    match network {
+        "ws" => {
+            let mut ws = Map::<String, Value>::new();
+            let mut path = ws_opts.and_then(|w| w.path.clone()).unwrap_or_default();
+            // Xray only sends early data in `Sec-WebSocket-Protocol`, asked for
+            // with an `ed` query on the path.
+            if let Some(max_early_data) = ws_opts.and_then(|w| w.max_early_data) {
+                let header = ws_opts.and_then(|w| w.early_data_header_name.as_deref());
+                if header.is_some_and(|h| h != "Sec-WebSocket-Protocol") {
+                    return Err(Unsupported::Feature(
+                        "early data outside `Sec-WebSocket-Protocol`",
+                    ));
+                }
+                let separator = if path.contains('?') { '&' } else { '?' };
+                path = format!("{path}{separator}ed={max_early_data}");
+            }
+            if !path.is_empty() {
+                ws.insert("path".to_string(), json!(path));
+            }
+            if let Some(headers) = ws_opts.and_then(|w| w.headers.as_ref()) {
+                ws.insert("headers".to_string(), json!(headers));
+            }
+            stream.insert("wsSettings".to_string(), Value::Object(ws));
+        }
+        "httpupgrade" => {
+            let mut upgrade = Map::<String, Value>::new();
+            if let Some(path) = ws_opts.and_then(|w| w.path.as_ref()) {
+                upgrade.insert("path".to_string(), json!(path));
+            }
+            if let Some(host) = ws_opts
+                .and_then(|w| w.headers.as_ref())
+                .and_then(|h| h.get("Host"))
+            {
+                upgrade.insert("host".to_string(), json!(host));
+            }
+            stream.insert("httpupgradeSettings".to_string(), Value::Object(upgrade));
+        }
+        "grpc" => {
+            let service_name = node
+                .grpc_opts
+                .as_ref()
+                .and_then(|g| g.grpc_service_name.as_deref())
+                .unwrap_or_default();
+            stream.insert(
+                "grpcSettings".to_string(),
+                json!({ "serviceName": service_name }),
+            );
+        }
+        "tcp" => {}
+        other => return Err(Unsupported::Transport(other.to_string())),
+    }
+
+    if node.tls.unwrap_or(false) || node.node_type == "trojan" {
+        stream.insert("security".to_string(), json!("tls"));
+        let mut tls = Map::<String, Value>::new();
+        if let Some(sni) = node.sni.as_deref().or(node.servername.as_deref()) {
+            tls.insert("serverName".to_string(), json!(sni));
+        }
+        if node.skip_cert_verify.unwrap_or(false) {
+            tls.insert("allowInsecure".to_string(), json!(true));
+        }
+        if let Some(alpn) = node.alpn.as_ref().filter(|a| !a.is_empty()) {
+            tls.insert("alpn".to_string(), json!(alpn));
+        }
+        if let Some(fingerprint) = &node.client_fingerprint {
+            tls.insert("fingerprint".to_string(), json!(fingerprint));
+        }
+        if !tls.is_empty() {
+            stream.insert("tlsSettings".to_string(), Value::Object(tls));
+        }
+    }
+    Ok(Value::Object(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Xray;
+    use crate::backend::{CoreBackend, CoreVersion, Egress, Unsupported};
+    use crate::chain::Chain;
+    use crate::config::AppConfig;
+    use crate::subscription::ProxyNode;
+
+    #[test]
+    fn renders_vmess_ws_with_direct_default() {
+        let node: ProxyNode = serde_yaml::from_str(
+            "{ name: n, type: vmess, server: v.example, port: 443, uuid: u-1, tls: true, network: ws, ws-opts: { path: /ws } }",
+        )
+        .unwrap();
+        let cfg = AppConfig::default();
+
+        let rendered = Xray
+            .render_config(
+                &cfg,
+                &Egress::Node(&node),
+                &Chain::default(),
+                27890,
+                false,
+                CoreVersion::new(25, 1, 30),
+            )
+            .unwrap();
+        let config: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(config["outbounds"][0]["tag"], "direct");
+        let proxy = &config["outbounds"][1];
+        assert_eq!(proxy["settings"]["vnext"][0]["users"][0]["id"], "u-1");
+        assert_eq!(proxy["streamSettings"]["wsSettings"]["path"], "/ws");
+        assert_eq!(proxy["streamSettings"]["security"], "tls");
+        assert_eq!(config["routing"]["rules"][0]["outboundTag"], "proxy");
+
+        let unsupported: ProxyNode =
+            serde_yaml::from_str("{ name: h, type: hysteria2, server: h.example, port: 443 }")
+                .unwrap();
+        assert_eq!(
+            Xray.check(&unsupported),
+            Err(Unsupported::Type("hysteria2".to_string()))
+        );
+    }
+}